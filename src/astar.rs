@@ -1,8 +1,9 @@
-use std::{collections::{HashMap, HashSet, BinaryHeap}, cmp::Ordering};
-use crate::field::{Field, Move};
+use std::{collections::{HashMap, HashSet, BinaryHeap}, cmp::Ordering, sync::Arc};
+use crate::field::{Field, Move, MAX_CELLS};
+use crate::heuristic::{Heuristic, Manhattan};
+use crate::solver::{replay, Budget, ProgressReporter, SearchStats, SolveError, SolveOptions, Solver, VerifyError};
 
 
-#[allow(dead_code)]
 #[derive(Clone, Copy)]
 struct AStarNode {
     id: usize,
@@ -15,39 +16,28 @@ struct AStarNode {
 }
 
 
-#[allow(dead_code)]
 pub fn calculate_heuristic(field: &Field) -> f32 {
-    (0_u8..16_u8).map(|i_target| {
-        let target_piece = (i_target + 1) % 16;
-        if target_piece == 0_u8 { return 0.0 }
-        for i_current in 0_u8..16_u8 {
-            if field.get_linear(i_current) == target_piece {
-                let target = [(i_target % 4) as i32, (i_target / 4) as i32];
-                let current = [(i_current % 4) as i32, (i_current / 4) as i32];
-                return ((target[0] - current[0]).abs() + (target[1] - current[1]).abs()) as f32;
-            }
-        }
-        0.0
+    let width = field.width() as i32;
+    (0..field.cell_count()).map(|i_current| {
+        let piece = field.get_linear(i_current);
+        if piece == 0_u8 { return 0.0 }
+        let i_target = (piece - 1) as i32;
+        let i_current = i_current as i32;
+        let target = [i_target % width, i_target / width];
+        let current = [i_current % width, i_current / width];
+        ((target[0] - current[0]).abs() + (target[1] - current[1]).abs()) as f32
     }).sum()
 }
 
-#[allow(dead_code)]
 pub fn calculate_heuristic_change(field: &Field, m: Move) -> f32 {
-    assert!(field.is_valid_move(m));
-
-    let empty_index = field.get_empty_index();
-    let piece_index = match m {
-        Move::Up => { empty_index - 4 }
-        Move::Down => { empty_index + 4 }
-        Move::Left => { empty_index - 1 }
-        Move::Right => { empty_index + 1 }
-    };
+    let piece_index = field.moved_piece_index(m).expect("invalid move");
+    let width = field.width() as i8;
 
-    let piece_pos = [(piece_index % 4) as i8, (piece_index / 4) as i8];
+    let piece_pos = [piece_index as i8 % width, piece_index as i8 / width];
     let piece = field.get_linear(piece_index);
     
-    let target_index = piece - 1;
-    let target_pos = [(target_index % 4) as i8, (target_index / 4) as i8];
+    let target_index = (piece - 1) as i8;
+    let target_pos = [target_index % width, target_index / width];
     
 
     match m {
@@ -70,7 +60,74 @@ pub fn calculate_heuristic_change(field: &Field, m: Move) -> f32 {
 }
 
 
-#[allow(unused)]
+/// Number of tiles that have to leave a row (`is_row`) or column to let the
+/// rest of its goal tiles pass each other: line length minus the longest
+/// run of tiles already in goal order. `target_of` gives a piece's goal index.
+fn line_conflicts<T: Fn(u8) -> u8>(field: &Field, is_row: bool, line: u8, target_of: &T) -> u8 {
+    let width = field.width();
+    let length = if is_row { width } else { field.height() };
+
+    let mut targets = [0_u8; MAX_CELLS];
+    let mut count = 0;
+    for k in 0..length {
+        let index = if is_row { line * width + k } else { k * width + line };
+        let piece = field.get_linear(index);
+        if piece == 0 { continue }
+
+        let target = target_of(piece);
+        let (target_line, target_k) = if is_row { (target / width, target % width) } else { (target % width, target / width) };
+        if target_line != line { continue }
+        targets[count] = target_k;
+        count += 1;
+    }
+
+    let mut longest = [1_u8; MAX_CELLS];
+    for i in 0..count {
+        for j in 0..i {
+            if targets[j] < targets[i] && longest[j] + 1 > longest[i] { longest[i] = longest[j] + 1 }
+        }
+    }
+
+    count as u8 - longest[..count].iter().copied().max().unwrap_or(0)
+}
+
+/// Two moves for every tile that has to step out of its goal row or column
+/// to get around another one.
+pub(crate) fn conflict_penalty<T: Fn(u8) -> u8>(field: &Field, target_of: &T) -> f32 {
+    let rows: u32 = (0..field.height()).map(|row| line_conflicts(field, true, row, target_of) as u32).sum();
+    let columns: u32 = (0..field.width()).map(|column| line_conflicts(field, false, column, target_of) as u32).sum();
+    2.0 * (rows + columns) as f32
+}
+
+pub(crate) fn conflict_penalty_change<T: Fn(u8) -> u8>(field: &Field, m: Move, target_of: &T) -> f32 {
+    let piece_index = field.moved_piece_index(m).expect("invalid move");
+    let empty_index = field.get_empty_index();
+    let width = field.width();
+
+    // Only the two lines the piece travels across can change their conflicts
+    let lines = match m {
+        Move::Up | Move::Down => [(true, piece_index / width), (true, empty_index / width)],
+        Move::Left | Move::Right => [(false, piece_index % width), (false, empty_index % width)],
+    };
+
+    let mut moved = *field;
+    moved.make_move(m);
+
+    let before: i32 = lines.iter().map(|&(is_row, line)| line_conflicts(field, is_row, line, target_of) as i32).sum();
+    let after: i32 = lines.iter().map(|&(is_row, line)| line_conflicts(&moved, is_row, line, target_of) as i32).sum();
+    2.0 * (after - before) as f32
+}
+
+/// Manhattan distance plus the linear conflict penalty.
+pub fn calculate_linear_conflict(field: &Field) -> f32 {
+    calculate_heuristic(field) + conflict_penalty(field, &|piece| piece - 1)
+}
+
+pub fn calculate_linear_conflict_change(field: &Field, m: Move) -> f32 {
+    calculate_heuristic_change(field, m) + conflict_penalty_change(field, m, &|piece| piece - 1)
+}
+
+
 impl AStarNode {
     fn new() -> AStarNode {
         AStarNode {
@@ -115,15 +172,16 @@ impl Eq for AStarNode {}
 
 impl PartialOrd for AStarNode {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        let result = self.heu.partial_cmp(&other.heu);
-        if let Some(result_unwrapped) = result { Some(result_unwrapped.reverse()) } else { result }
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for AStarNode {
     fn cmp(&self, other: &Self) -> Ordering {
         assert!(self.heu.partial_cmp(&other.heu).is_some());
+        // Lowest f first, ties go to the deeper node
         self.heu.partial_cmp(&other.heu).unwrap().reverse()
+            .then_with(|| self.heu_g.partial_cmp(&other.heu_g).unwrap())
     }
 }
 
@@ -132,52 +190,148 @@ impl Ord for AStarNode {
 pub struct AStarSolution {
     pub moves: Vec<Move>,
     pub states: Vec<Field>,
+    pub stats: SearchStats,
 }
 
+impl AStarSolution {
+    /// Replays `moves` from `start` to fill in the states along the way.
+    pub fn from_moves(start: &Field, moves: Vec<Move>, stats: SearchStats) -> AStarSolution {
+        let mut field = *start;
+        let mut states = vec![field];
+        for &m in &moves {
+            field.make_move(m);
+            states.push(field);
+        }
+        AStarSolution { moves, states, stats }
+    }
 
-pub struct AStar {
+    /// Checks that the moves solve `states[0]` toward `goal`, and that
+    /// `states` holds exactly the boards they pass through.
+    pub fn verify(&self, goal: &Field) -> Result<(), VerifyError> {
+        let Some(start) = self.states.first() else { return Err(VerifyError::WrongState { index: 0 }) };
+        let replayed = replay(start, &self.moves)?;
+        if let Some(index) = (0..replayed.len().max(self.states.len())).find(|&i| replayed.get(i) != self.states.get(i)) {
+            return Err(VerifyError::WrongState { index });
+        }
+        let board = *replayed.last().unwrap();
+        if board != *goal { return Err(VerifyError::NotSolved { board }) }
+        Ok(())
+    }
+}
+
+
+pub struct AStar<H: Heuristic = Manhattan> {
     nodes: Vec<AStarNode>,
     open: BinaryHeap<AStarNode>,
-    added: HashSet<u64>,
-    closed: HashMap<u64, usize>,
+    best_g: HashMap<u128, f32>,
+    heuristic: H,
+    goal: Field,
+    /// Nodes are ordered by g + weight * h. Above 1 finds a solution sooner,
+    /// but it can be up to `weight` times longer than the optimal one.
+    weight: f32,
+    /// Nodes whose unweighted g + h reach this can't lead to anything shorter
+    /// than a solution we already have and are dropped.
+    prune_at: f32,
+    stats: SearchStats,
+    reporter: ProgressReporter,
 }
 
-#[allow(unused)]
 impl AStar {
     pub fn new(start: &Field) -> AStar {
+        AStar::with_heuristic(start, Manhattan)
+    }
+}
+
+impl<H: Heuristic> AStar<H> {
+    pub fn with_heuristic(start: &Field, heuristic: H) -> AStar<H> {
         let mut astar = AStar {
             nodes: Default::default(),
             open: Default::default(),
-            added: Default::default(),
-            closed: Default::default(),
+            best_g: Default::default(),
+            goal: heuristic.goal(start.width(), start.height()),
+            heuristic,
+            weight: 1.0,
+            prune_at: f32::INFINITY,
+            stats: Default::default(),
+            reporter: ProgressReporter::new(&SolveOptions::default()),
         };
+        astar.reset(start);
+        astar
+    }
+
+    /// Weighted A*: orders nodes by g + weight * h.
+    pub fn with_weight(mut self, weight: f32) -> AStar<H> {
+        self.weight = weight;
+        let start = self.nodes[0].field;
+        self.reset(&start);
+        self
+    }
+
+    /// Throws away the search so far and starts over from `start`.
+    pub fn reset(&mut self, start: &Field) {
+        self.nodes.clear();
+        self.open.clear();
+        self.best_g.clear();
+        self.prune_at = f32::INFINITY;
+        self.goal = self.heuristic.goal(start.width(), start.height());
+        self.stats = SearchStats::default();
+        self.reporter.restart();
 
-        
         let mut node = AStarNode::new();
-        node.field = start.clone();
-        node.heu_h = calculate_heuristic(start);
+        node.field = *start;
+        node.heu_h = self.heuristic.evaluate(start);
         node.heu_g = 0.0;
-        node.heu = node.heu_h;
-        node.id = astar.nodes.len();
-        node.parent_id = astar.nodes.len();
-        astar.nodes.push(node);
-        astar.open.push(node);
-        astar.added.insert(start.unique_id());
+        node.heu = self.weight * node.heu_h;
+        node.id = self.nodes.len();
+        node.parent_id = self.nodes.len();
+        self.nodes.push(node);
+        self.open.push(node);
+        self.best_g.insert(start.unique_id(), 0.0);
+        self.stats.peak_open = 1;
+    }
 
-        astar
+    pub fn stats(&self) -> SearchStats {
+        let mut stats = self.stats;
+        self.reporter.finish(&mut stats);
+        stats
+    }
+
+    /// Rough number of bytes held by the nodes, the open list and the best g of every board seen.
+    pub fn memory_usage(&self) -> usize {
+        let node = std::mem::size_of::<AStarNode>();
+        let entry = std::mem::size_of::<(u128, f32)>() + 1;
+        (self.nodes.capacity() + self.open.capacity()) * node + self.best_g.capacity() * entry
+    }
+
+    /// Fewest moves any solution not found yet can take, going by the
+    /// unweighted estimates of the open nodes. Walks the whole open list.
+    pub fn lower_bound(&self) -> f32 {
+        self.open.iter()
+            .filter(|node| self.best_g.get(&node.field.unique_id()).is_none_or(|&g| g >= node.heu_g))
+            .map(|node| node.heu_g + node.heu_h)
+            .fold(self.prune_at, f32::min)
     }
 
     pub fn step(&mut self) -> Option<Result<AStarSolution, ()>> {
         let Some(current_node) = self.open.pop() else { return Some(Err(())) };
 
+        // Stale entry, the state was pushed again with a shorter path since
+        if self.best_g.get(&current_node.field.unique_id()).is_some_and(|&g| g < current_node.heu_g) { return None }
+        if current_node.heu_g + current_node.heu_h >= self.prune_at { return None }
+
+        // The open list's least g + weight * h, over the weight, is at most its least g + h
+        let bound = (current_node.heu / self.weight.max(1.0)).min(self.prune_at);
+        self.stats.bound = self.stats.bound.max(bound);
+        self.stats.depth = self.stats.depth.max(current_node.heu_g as usize);
+
         // Goal Reached
-        if current_node.field.is_solved() {
+        if current_node.field == self.goal {
             let mut node = &current_node;
             let mut prev_node_option: Option<&AStarNode> = None;
             let mut states = Vec::new();
             let mut moves = Vec::new();
             loop {
-                states.push(node.field.clone());
+                states.push(node.field);
                 
                 if let Some(prev_node) = prev_node_option {
                     moves.push(prev_node.previous_move.unwrap());
@@ -186,29 +340,30 @@ impl AStar {
                 let next_node = self.nodes.get(node.parent_id);
                 if next_node.is_none() { break }
                 if node == next_node.unwrap() { break }
-                prev_node_option = Some(&node);
+                prev_node_option = Some(node);
                 node = next_node.unwrap();
             }
             
             moves.reverse();
             states.reverse();
-            return Some(Ok(AStarSolution {moves, states}));
+            return Some(Ok(AStarSolution {moves, states, stats: self.stats()}));
         }
 
-        assert!(self.closed.get(&current_node.field.unique_id()).is_none(), "wtf is that = {:#01x}", current_node.field.unique_id());
-        self.closed.insert(current_node.field.unique_id(), current_node.id);
+        self.stats.expanded += 1;
 
         Move::iter().for_each(|m| {
             if current_node.previous_move.is_some() && current_node.previous_move.unwrap() == m.inverse() { return }
             if !current_node.field.is_valid_move(m) { return }
 
-            let mut field: Field = current_node.field.clone();
+            let mut field: Field = current_node.field;
             let heu_g = current_node.heu_g + 1.0;
-            let heu_h = current_node.heu_h + calculate_heuristic_change(&field, m);
-            let heu = heu_g + heu_h;
+            let heu_h = self.heuristic.next(&field, current_node.heu_h, m);
+            let heu = heu_g + self.weight * heu_h;
             field.make_move(m);
+            if heu_g + heu_h >= self.prune_at { return }
             
-            if self.added.contains(&field.unique_id()) { return }
+            // Only (re)open states we have found a shorter path to
+            if self.best_g.get(&field.unique_id()).is_some_and(|&g| g <= heu_g) { return }
 
             let mut node = AStarNode::from(field);
             node.id = self.nodes.len();
@@ -220,126 +375,334 @@ impl AStar {
             self.nodes.push(node);
 
             self.open.push(node);
-            self.added.insert(field.unique_id());
+            self.best_g.insert(field.unique_id(), heu_g);
+            self.stats.generated += 1;
         });
 
+        self.stats.peak_open = self.stats.peak_open.max(self.open.len());
+        self.reporter.tick(&mut self.stats);
         None
     }
 }
 
 
 
-pub struct IDAStar {
-    nodes: Vec<AStarNode>,
-    closed: HashSet<u64>,
-    start: Field,
+impl<H: Heuristic> Solver for AStar<H> {
+    fn solve(&mut self, start: &Field, options: &SolveOptions) -> Result<AStarSolution, SolveError> {
+        if !start.is_solvable_toward(&self.heuristic.goal(start.width(), start.height())) { return Err(SolveError::Unsolvable) }
+        self.reporter = ProgressReporter::new(options);
+        self.reset(start);
+
+        let budget = Budget::new(options);
+        loop {
+            if let Err(error) = budget.check(&self.stats, self.memory_usage()) { return Err(self.reporter.finish_error(error)) }
+            match self.step() {
+                Some(Ok(solution)) => return Ok(solution),
+                Some(Err(())) => return Err(SolveError::Unsolvable),
+                None => {}
+            }
+        }
+    }
 }
 
 
-#[allow(unused)]
-impl IDAStar {
-    pub fn new(start: &Field) -> IDAStar {
-        let mut idastar = IDAStar {
-            nodes: Default::default(),
-            closed: Default::default(),
-            start: start.clone(),
-        };
-        idastar
+
+pub type ImprovementCallback = Arc<dyn Fn(&AStarSolution, f32) + Send + Sync>;
+
+
+/// Anytime weighted A*. Finds a first solution quickly with weighted A*, then
+/// keeps searching and only keeps nodes that could still beat the best
+/// solution so far. Each improvement comes with a bound on how many times
+/// longer than optimal it can be. Stops once that bound reaches 1, or returns
+/// the best solution so far when it runs out of budget.
+pub struct AnytimeAStar<H: Heuristic = Manhattan> {
+    astar: AStar<H>,
+    best: Option<AStarSolution>,
+    suboptimality: f32,
+    on_improvement: Option<ImprovementCallback>,
+}
+
+
+impl AnytimeAStar {
+    pub fn new(start: &Field, weight: f32) -> AnytimeAStar {
+        AnytimeAStar::with_heuristic(start, Manhattan, weight)
     }
+}
 
-    pub fn run(&mut self) -> Result<AStarSolution, ()> {
-        let root = AStarNode {
-            field: self.start.clone(),
-            heu_h: 0.0, heu_g: 0.0, heu: 0.0, //ignore
-            id: self.nodes.len(),
-            parent_id: self.nodes.len(),
-            
-            previous_move: None,
-        };
-        
-        self.nodes.push(root);
-        
-        let mut bound = calculate_heuristic(&self.start);
+
+impl<H: Heuristic> AnytimeAStar<H> {
+    pub fn with_heuristic(start: &Field, heuristic: H, weight: f32) -> AnytimeAStar<H> {
+        AnytimeAStar {
+            astar: AStar::with_heuristic(start, heuristic).with_weight(weight),
+            best: None,
+            suboptimality: f32::INFINITY,
+            on_improvement: None,
+        }
+    }
+
+    /// Called with every better solution and its suboptimality bound.
+    pub fn on_improvement(mut self, callback: ImprovementCallback) -> AnytimeAStar<H> {
+        self.on_improvement = Some(callback);
+        self
+    }
+
+    pub fn best(&self) -> Option<&AStarSolution> {
+        self.best.as_ref()
+    }
+
+    /// The best solution is at most this many times longer than the optimal one.
+    pub fn suboptimality(&self) -> f32 {
+        self.suboptimality
+    }
+
+    fn update_suboptimality(&mut self) {
+        let Some(best) = &self.best else { return };
+        let cost = best.moves.len() as f32;
+        let lower_bound = self.astar.lower_bound();
+        self.suboptimality = if lower_bound >= cost { 1.0 } else { cost / lower_bound };
+    }
+
+    fn take_best(&mut self) -> Option<AStarSolution> {
+        let mut best = self.best.take()?;
+        best.stats = self.astar.stats();
+        Some(best)
+    }
+}
+
+
+impl<H: Heuristic> Solver for AnytimeAStar<H> {
+    fn solve(&mut self, start: &Field, options: &SolveOptions) -> Result<AStarSolution, SolveError> {
+        if !start.is_solvable_toward(&self.astar.heuristic.goal(start.width(), start.height())) { return Err(SolveError::Unsolvable) }
+        self.astar.reporter = ProgressReporter::new(options);
+        self.astar.reset(start);
+        self.best = None;
+        self.suboptimality = f32::INFINITY;
+
+        let budget = Budget::new(options);
         loop {
-            match self.search(root.id, 0.0, bound) {
-                Ok(solution) => return Ok(solution),
-                Err(t) => {
-                    if t.is_infinite() { return Err(()) }
-                    bound = t;
+            if let Err(error) = budget.check(&self.astar.stats, self.astar.memory_usage()) {
+                self.update_suboptimality();
+                return self.take_best().ok_or_else(|| self.astar.reporter.finish_error(error));
+            }
+
+            match self.astar.step() {
+                Some(Ok(solution)) => {
+                    self.astar.prune_at = solution.moves.len() as f32;
+                    self.best = Some(solution);
+                    self.update_suboptimality();
+                    if let (Some(callback), Some(best)) = (&self.on_improvement, &self.best) { callback(best, self.suboptimality) }
+                    if self.suboptimality <= 1.0 { return Ok(self.take_best().unwrap()) }
+                }
+                // Nothing left that could do better
+                Some(Err(())) => {
+                    self.suboptimality = 1.0;
+                    return self.take_best().ok_or(SolveError::Unsolvable);
                 }
+                None => {}
             }
         }
     }
+}
 
 
-    fn search(&mut self, current_node_id: usize, g: f32, bound: f32) -> Result<AStarSolution, f32> {
-        let current_node = self.nodes.get(current_node_id).unwrap();
-        let f = g + calculate_heuristic(&current_node.field);
-        if f > bound { return Err(f) }
-        if current_node.field.is_solved() {
-            let mut node = &current_node.clone();
-            let mut prev_node_option: Option<&AStarNode> = None;
-            let mut states = Vec::new();
-            let mut moves = Vec::new();
-            loop {
-                states.push(node.field.clone());
-                
-                if let Some(prev_node) = prev_node_option {
-                    moves.push(prev_node.previous_move.unwrap());
-                }
 
-                let next_node = self.nodes.get(node.parent_id);
-                if next_node.is_none() { break }
-                if node == next_node.unwrap() { break }
-                prev_node_option = Some(&node);
-                node = next_node.unwrap();
-            }
-            
-            moves.reverse();
-            states.reverse();
-            return Ok(AStarSolution {moves, states});
+/// Depth-first iterative deepening. Only the current path is kept: moves are
+/// made and undone on a single board, so memory stays flat across iterations.
+pub struct IDAStar<H: Heuristic = Manhattan> {
+    start: Field,
+    heuristic: H,
+    goal: Field,
+    field: Field,
+    path: Vec<Move>,
+    budget: Budget,
+    stats: SearchStats,
+    reporter: ProgressReporter,
+    collector: Option<Collector>,
+}
+
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct AllSolutionsOptions {
+    /// Most solutions to keep. All of them are still counted.
+    pub max_solutions: Option<usize>,
+    /// Skip boards already reached at the same depth, so no two solutions
+    /// share a board other than the start and the goal.
+    pub remove_transpositions: bool,
+}
+
+/// Every optimal solution, or as many as were asked for.
+pub struct OptimalSolutions {
+    pub length: usize,
+    pub count: u64,
+    /// In the order they were found.
+    pub solutions: Vec<Vec<Move>>,
+    pub stats: SearchStats,
+}
+
+/// Solutions found by an iteration that searches on past the first one.
+struct Collector {
+    count: u64,
+    first: Option<Vec<Move>>,
+    solutions: Vec<Vec<Move>>,
+    max_solutions: Option<usize>,
+    /// (board, depth) pairs reached in this iteration, when removing transpositions.
+    seen: Option<HashSet<(u128, usize)>>,
+}
+
+impl Collector {
+    fn record(&mut self, path: &[Move]) {
+        self.count += 1;
+        if self.first.is_none() { self.first = Some(path.to_vec()) }
+        if self.max_solutions.is_none_or(|max| self.solutions.len() < max) { self.solutions.push(path.to_vec()) }
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.seen.as_ref().map_or(0, |seen| seen.capacity() * (std::mem::size_of::<(u128, usize)>() + 1))
+            + self.solutions.iter().map(|path| path.capacity()).sum::<usize>()
+    }
+}
+
+
+enum SearchResult {
+    Found,
+    Exceeded(f32),
+    Stopped(SolveError),
+}
+
+
+impl IDAStar {
+    pub fn new(start: &Field) -> IDAStar {
+        IDAStar::with_heuristic(start, Manhattan)
+    }
+}
+
+
+impl<H: Heuristic> IDAStar<H> {
+    pub fn with_heuristic(start: &Field, heuristic: H) -> IDAStar<H> {
+        IDAStar {
+            start: *start,
+            goal: heuristic.goal(start.width(), start.height()),
+            heuristic,
+            field: *start,
+            path: Vec::new(),
+            budget: Budget::unlimited(),
+            stats: Default::default(),
+            reporter: ProgressReporter::new(&SolveOptions::default()),
+            collector: None,
         }
+    }
+
+    pub fn run(&mut self) -> Result<AStarSolution, ()> {
+        let start = self.start;
+        self.solve(&start, &SolveOptions::default()).map_err(|_| ())
+    }
+
+
+    /// Finds every optimal solution instead of stopping at the first: the
+    /// iteration that reaches the goal is searched to the end.
+    pub fn solve_all(&mut self, start: &Field, options: &SolveOptions, all: &AllSolutionsOptions) -> Result<OptimalSolutions, SolveError> {
+        self.collector = Some(Collector {
+            count: 0,
+            first: None,
+            solutions: Vec::new(),
+            max_solutions: all.max_solutions,
+            seen: all.remove_transpositions.then(HashSet::new),
+        });
+        let result = self.solve(start, options);
+        let collector = self.collector.take().unwrap();
+
+        let solution = result?;
+        Ok(OptimalSolutions { length: solution.moves.len(), count: collector.count, solutions: collector.solutions, stats: solution.stats })
+    }
+
+
+    fn search(&mut self, g: f32, h: f32, bound: f32) -> SearchResult {
+        let f = g + h;
+        if f > bound { return SearchResult::Exceeded(f) }
+        if self.field == self.goal {
+            let Some(collector) = &mut self.collector else { return SearchResult::Found };
+            collector.record(&self.path);
+            return SearchResult::Exceeded(f32::INFINITY);
+        }
+        if let Some(seen) = self.collector.as_mut().and_then(|collector| collector.seen.as_mut()) {
+            if !seen.insert((self.field.unique_id(), self.path.len())) { return SearchResult::Exceeded(f32::INFINITY) }
+        }
+
+        let memory = self.path.capacity() + self.collector.as_ref().map_or(0, Collector::memory_usage);
+        if let Err(error) = self.budget.check(&self.stats, memory) { return SearchResult::Stopped(self.reporter.finish_error(error)) }
+        self.stats.expanded += 1;
+        self.stats.depth = self.stats.depth.max(self.path.len());
+        self.stats.peak_open = self.stats.depth;
+        self.reporter.tick(&mut self.stats);
 
         let mut min = f32::INFINITY;
-        let successors = self.successors(current_node_id);
-        for x_id in successors {
-            match self.search(x_id, g + 1.0, bound) {
-                Ok(solution) => return Ok(solution),
-                Err(t) => {
+        for m in Move::iter() {
+            if self.path.last() == Some(&m.inverse()) { continue }
+            if !self.field.is_valid_move(m) { continue }
+
+            let child_h = self.heuristic.next(&self.field, h, m);
+            self.field.make_move(m);
+            self.path.push(m);
+            self.stats.generated += 1;
+
+            match self.search(g + 1.0, child_h, bound) {
+                SearchResult::Exceeded(t) => {
                     if t < min { min = t }
                 }
+                result => return result,
             }
+
+            self.path.pop();
+            self.field.make_move(m.inverse());
         }
 
-        return Err(min);
+        SearchResult::Exceeded(min)
     }
 
 
-    fn successors(&mut self, node_id: usize) -> Vec<usize> {
-        let node = self.nodes.get(node_id).unwrap().clone();
-        let mut v = Vec::new();
-        for m in Move::iter() {
-            if node.previous_move.is_some() && node.previous_move.unwrap() == m.inverse() { continue }
-            if !node.field.is_valid_move(m) { continue }
+    fn solution(&mut self) -> AStarSolution {
+        self.reporter.finish(&mut self.stats);
+        AStarSolution::from_moves(&self.start, self.path.clone(), self.stats)
+    }
 
-            let mut field: Field = node.field.clone();
-            let heu_g = node.heu_g + 1.0;
-            let heu_h = node.heu_h + calculate_heuristic_change(&field, m);
-            let heu = heu_g + heu_h;
-            field.make_move(m);
-            
-            let id = field.unique_id();
-            // if self.closed.contains(&id) { continue }
-            // self.closed.insert(id);
-
-            let mut new_node = AStarNode::from(field);
-            new_node.id = self.nodes.len();
-            new_node.parent_id = node.id;
-            new_node.previous_move = Some(m);
-            self.nodes.push(new_node);
-            v.push(new_node.id);
+    pub fn stats(&self) -> SearchStats {
+        let mut stats = self.stats;
+        self.reporter.finish(&mut stats);
+        stats
+    }
+}
+
+
+impl<H: Heuristic> Solver for IDAStar<H> {
+    fn solve(&mut self, start: &Field, options: &SolveOptions) -> Result<AStarSolution, SolveError> {
+        self.goal = self.heuristic.goal(start.width(), start.height());
+        if !start.is_solvable_toward(&self.goal) { return Err(SolveError::Unsolvable) }
+        self.start = *start;
+        self.field = *start;
+        self.path.clear();
+        self.budget = Budget::new(options);
+        self.stats = SearchStats::default();
+        self.reporter = ProgressReporter::new(options);
+
+        let h = self.heuristic.evaluate(start);
+        let mut bound = h;
+        loop {
+            self.stats.bound = bound;
+            self.reporter.report(&mut self.stats);
+            if let Some(seen) = self.collector.as_mut().and_then(|collector| collector.seen.as_mut()) { seen.clear() }
+            match self.search(0.0, h, bound) {
+                SearchResult::Found => return Ok(self.solution()),
+                SearchResult::Stopped(error) => return Err(error),
+                SearchResult::Exceeded(t) => {
+                    if let Some(first) = self.collector.as_ref().and_then(|collector| collector.first.clone()) {
+                        self.path = first;
+                        return Ok(self.solution());
+                    }
+                    if t.is_infinite() { return Err(SolveError::Unsolvable) }
+                    bound = t;
+                }
+            }
         }
-        v
     }
 }
 
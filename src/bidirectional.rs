@@ -0,0 +1,247 @@
+use std::{collections::{BTreeMap, BinaryHeap, HashMap}, cmp::Ordering};
+use crate::astar::AStarSolution;
+use crate::field::{Field, Move};
+use crate::heuristic::{GoalLinearConflict, Heuristic, Manhattan};
+use crate::solver::{Budget, ProgressReporter, SearchStats, SolveError, SolveOptions, Solver};
+
+
+#[derive(Clone, Copy)]
+struct Node {
+    parent: usize,
+    field: Field,
+    g: f32,
+    h: f32,
+    previous_move: Option<Move>,
+}
+
+
+#[derive(Clone, Copy, PartialEq)]
+struct OpenEntry {
+    f: f32,
+    g: f32,
+    node: usize,
+}
+
+impl Eq for OpenEntry {}
+
+impl PartialOrd for OpenEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpenEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Lowest f first, ties go to the deeper node
+        self.f.total_cmp(&other.f).reverse()
+            .then_with(|| self.g.total_cmp(&other.g))
+    }
+}
+
+
+/// One direction of the search: an A* over its own nodes, keyed by `unique_id`.
+#[derive(Default)]
+struct Side {
+    nodes: Vec<Node>,
+    open: BinaryHeap<OpenEntry>,
+    /// Best g found so far for every state seen, with the node that reached it.
+    best: HashMap<u128, (f32, usize)>,
+    /// How many open entries have each g, keyed by `f32::to_bits` which sorts
+    /// the same as the (non-negative) values themselves.
+    open_g: BTreeMap<u32, usize>,
+}
+
+impl Side {
+    fn reset(&mut self, root: &Field, h: f32) {
+        self.nodes.clear();
+        self.open.clear();
+        self.best.clear();
+        self.open_g.clear();
+        self.nodes.push(Node { parent: 0, field: *root, g: 0.0, h, previous_move: None });
+        self.push(0);
+        self.best.insert(root.unique_id(), (0.0, 0));
+    }
+
+    fn push(&mut self, node: usize) {
+        let Node { g, h, .. } = self.nodes[node];
+        self.open.push(OpenEntry { f: g + h, g, node });
+        *self.open_g.entry(g.to_bits()).or_default() += 1;
+    }
+
+    fn pop(&mut self) -> Option<(usize, Node)> {
+        let index = self.open.pop()?.node;
+        let node = self.nodes[index];
+        let count = self.open_g.get_mut(&node.g.to_bits()).unwrap();
+        *count -= 1;
+        if *count == 0 { self.open_g.remove(&node.g.to_bits()); }
+        Some((index, node))
+    }
+
+    /// Lowest f and lowest g among the nodes still waiting to be expanded.
+    fn min_open(&self) -> (f32, f32) {
+        let f = self.open.peek().map_or(f32::INFINITY, |entry| entry.f);
+        let g = self.open_g.keys().next().map_or(f32::INFINITY, |&bits| f32::from_bits(bits));
+        (f, g)
+    }
+
+    /// Moves from this side's root down to `node`.
+    fn path(&self, mut node: usize) -> Vec<Move> {
+        let mut moves = Vec::new();
+        while let Some(m) = self.nodes[node].previous_move {
+            moves.push(m);
+            node = self.nodes[node].parent;
+        }
+        moves.reverse();
+        moves
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.nodes.capacity() * std::mem::size_of::<Node>()
+            + self.open.capacity() * std::mem::size_of::<OpenEntry>()
+            + self.best.capacity() * (std::mem::size_of::<(u128, (f32, usize))>() + 1)
+    }
+}
+
+
+/// A* from both ends at once: forward from the scrambled board with `H`, and
+/// backward from `H`'s goal with the linear conflict distance to the start.
+/// Every generated state is looked up in the other side's table, and the
+/// search stops once no unexpanded node can lead to a shorter meeting than the
+/// best one found. Needs fewer bytes per node than `AStar`, so it gets
+/// through deeper boards in the same memory. Optimal when `H` is admissible.
+pub struct BidirectionalAStar<H: Heuristic = Manhattan> {
+    heuristic: H,
+    forward: Side,
+    backward: Side,
+    stats: SearchStats,
+    reporter: ProgressReporter,
+}
+
+
+impl BidirectionalAStar {
+    pub fn new() -> BidirectionalAStar {
+        BidirectionalAStar::with_heuristic(Manhattan)
+    }
+}
+
+impl Default for BidirectionalAStar {
+    fn default() -> Self {
+        BidirectionalAStar::new()
+    }
+}
+
+
+impl<H: Heuristic> BidirectionalAStar<H> {
+    pub fn with_heuristic(heuristic: H) -> BidirectionalAStar<H> {
+        BidirectionalAStar {
+            heuristic,
+            forward: Side::default(),
+            backward: Side::default(),
+            stats: SearchStats::default(),
+            reporter: ProgressReporter::new(&SolveOptions::default()),
+        }
+    }
+
+    pub fn stats(&self) -> SearchStats {
+        let mut stats = self.stats;
+        self.reporter.finish(&mut stats);
+        stats
+    }
+
+    /// Rough number of bytes held by both halves of the search.
+    pub fn memory_usage(&self) -> usize {
+        self.forward.memory_usage() + self.backward.memory_usage()
+    }
+
+
+    /// Expands the best open node of one side. Returns the cost and the
+    /// (forward, backward) node pair of the shortest meeting it found, if any.
+    fn expand(&mut self, is_forward: bool, target: &GoalLinearConflict) -> Option<(f32, usize, usize)> {
+        let (side, other) = if is_forward { (&mut self.forward, &self.backward) } else { (&mut self.backward, &self.forward) };
+        let (index, current) = side.pop()?;
+
+        // Stale entry, the state was pushed again with a shorter path since
+        if side.best.get(&current.field.unique_id()).is_some_and(|&(g, _)| g < current.g) { return None }
+
+        self.stats.expanded += 1;
+        let mut meeting: Option<(f32, usize, usize)> = None;
+        for m in Move::iter() {
+            if current.previous_move == Some(m.inverse()) { continue }
+            if !current.field.is_valid_move(m) { continue }
+
+            let g = current.g + 1.0;
+            let h = if is_forward { self.heuristic.next(&current.field, current.h, m) } else { target.next(&current.field, current.h, m) };
+            let mut field = current.field;
+            field.make_move(m);
+
+            let id = field.unique_id();
+            if side.best.get(&id).is_some_and(|&(best_g, _)| best_g <= g) { continue }
+
+            let node = side.nodes.len();
+            side.nodes.push(Node { parent: index, field, g, h, previous_move: Some(m) });
+            side.push(node);
+            side.best.insert(id, (g, node));
+            self.stats.generated += 1;
+            self.stats.depth = self.stats.depth.max(g as usize);
+
+            if let Some(&(other_g, other_node)) = other.best.get(&id) {
+                let cost = g + other_g;
+                if meeting.is_none_or(|(best, _, _)| cost < best) {
+                    meeting = Some(if is_forward { (cost, node, other_node) } else { (cost, other_node, node) });
+                }
+            }
+        }
+        meeting
+    }
+
+    /// Forward path to the meeting state followed by the backward path undone.
+    fn stitch(&self, start: &Field, forward_node: usize, backward_node: usize) -> AStarSolution {
+        let mut moves = self.forward.path(forward_node);
+        moves.extend(self.backward.path(backward_node).iter().rev().map(Move::inverse));
+
+        let mut stats = self.stats;
+        self.reporter.finish(&mut stats);
+        AStarSolution::from_moves(start, moves, stats)
+    }
+}
+
+
+impl<H: Heuristic> Solver for BidirectionalAStar<H> {
+    fn solve(&mut self, start: &Field, options: &SolveOptions) -> Result<AStarSolution, SolveError> {
+        let goal = self.heuristic.goal(start.width(), start.height());
+        if !start.is_solvable_toward(&goal) { return Err(SolveError::Unsolvable) }
+        if *start == goal { return Ok(AStarSolution::from_moves(start, Vec::new(), SearchStats::default())) }
+
+        let target = GoalLinearConflict::new(start);
+        self.forward.reset(start, self.heuristic.evaluate(start));
+        self.backward.reset(&goal, target.evaluate(&goal));
+        self.stats = SearchStats { peak_open: 2, ..Default::default() };
+        self.reporter = ProgressReporter::new(options);
+        let budget = Budget::new(options);
+
+        let mut best: Option<(f32, usize, usize)> = None;
+        loop {
+            let (forward_f, forward_g) = self.forward.min_open();
+            let (backward_f, backward_g) = self.backward.min_open();
+            // Any path not found yet runs through an open node on both sides, so
+            // it's no shorter than any of these. Every move costs one.
+            let lower_bound = forward_f.max(backward_f).max(forward_g + backward_g + 1.0);
+            self.stats.bound = lower_bound.min(best.map_or(f32::INFINITY, |(cost, _, _)| cost));
+            if let Some((cost, forward_node, backward_node)) = best {
+                if cost <= lower_bound { return Ok(self.stitch(start, forward_node, backward_node)) }
+            }
+            if lower_bound.is_infinite() { return Err(SolveError::Unsolvable) }
+
+            if let Err(error) = budget.check(&self.stats, self.memory_usage()) { return Err(self.reporter.finish_error(error)) }
+
+            // Grow whichever side is smaller
+            let is_forward = self.forward.open.len() <= self.backward.open.len();
+            if let Some(meeting) = self.expand(is_forward, &target) {
+                if best.is_none_or(|(cost, _, _)| meeting.0 < cost) { best = Some(meeting) }
+            }
+
+            self.stats.peak_open = self.stats.peak_open.max(self.forward.open.len() + self.backward.open.len());
+            self.reporter.tick(&mut self.stats);
+        }
+    }
+}
@@ -0,0 +1,802 @@
+use std::{collections::{hash_map::Entry, HashMap}, env, fmt::Write as _, fs::{self, File}, io::{self, BufRead, BufReader, BufWriter, Write}, path::{Path, PathBuf}, process::ExitCode, str::FromStr, sync::Arc, time::{Duration, Instant}};
+use pyatnahi::astar::{AStar, AStarSolution, AllSolutionsOptions, AnytimeAStar, IDAStar};
+use pyatnahi::bidirectional::BidirectionalAStar;
+use pyatnahi::enumerate::{self, StateSpace};
+use pyatnahi::field::{Field, Move};
+use pyatnahi::moves::{MoveSequence, Notation};
+use pyatnahi::heuristic::{GoalLinearConflict, GoalManhattan, HeuristicKind};
+use pyatnahi::parallel::ParallelIDAStar;
+use pyatnahi::pdb::{self, PatternDatabase};
+use pyatnahi::scramble::{self, Rng};
+use pyatnahi::solver::{self, SearchStats, SolveError, SolveOptions, Solver};
+
+
+pub const USAGE: &str = "\
+Usage: pyatnahi [COMMAND] [OPTIONS]
+
+Commands:
+  solve <board>           Solve a board and print the moves
+  play <board>            Open a window and play the board yourself. H marks
+                          the tile to move next, D shows the moves left
+  watch <board>           Solve a board and play the solution back in a window.
+                          Space plays and pauses, left and right step, up and
+                          down change the speed, Home starts over
+  generate                Print scrambled boards
+  verify <board> <moves>  Check that the moves solve the board
+  bench <file>            Solve every board in a file, one per line, and time it
+  batch <file>            Solve every board in a file (- for stdin) as it's read,
+                          one result per line. Bad boards get an error line
+  enumerate               Find every board of --size (default 3x3, at most 12
+                          cells) breadth first and count them by distance
+  help                    Show this message
+Without a command the board and what to do with it are asked for interactively.
+play and watch need a build with the gui feature.
+
+Boards are written row by row with one base-36 digit per cell and 0 for the
+blank, like 123456789ABCDEF0. Moves are ^v<> or UDLR and move the blank.
+
+Options:
+  --algorithm <name>   astar, ida, parallel-ida (default), bidirectional, weighted, anytime
+  --heuristic <name>   auto (default), manhattan, linear-conflict, pdb. auto tries
+                       linear conflict briefly, then a pattern database on 4x4
+  --pdb <file>         Where the pattern database is loaded from and saved to.
+                       Default a file per size and goal in the user's cache directory
+  --format <format>    text (default) or json. batch also takes csv, and writes
+                       json as one object per line
+  --notation <name>    How moves are written: arrows (default), udlr, compressed
+                       (^3<2 for ^^^<<) or tiles (the numbers of the tiles moved).
+                       Moves given to verify can be in any of them
+  --output <file>      Where batch writes its results. Default stdout
+  --size <WxH>         Board size, needed for boards that aren't square. Default 4x4 for generate
+  --goal <board>       Board to solve toward: solved (default), spiral, blank-first or a board
+  --weight <w>         Heuristic weight for weighted and anytime. Default 2
+  --threads <n>        Threads for parallel-ida. Default all cores
+  --max-time <secs>    Give up after this long. Anytime returns its best solution so far
+  --max-nodes <n>      Give up after expanding this many nodes
+  --scramble <kind>    How generate scrambles: walk (default) makes --moves random
+                       moves from the goal, uniform picks any solvable board, and
+                       optimal finds a board whose shortest solution is --moves long
+  --moves <n>          Moves for generate's scramble. Default 200 for walk, and
+                       needs to be given for optimal
+  --count <n>          Boards generate prints. Default 1
+  --seed <n>           Seed for generate. Default taken from the clock
+  --all                Have solve find every optimal solution with IDA*, not just one
+  --max-solutions <n>  Most solutions --all prints. All of them are still counted
+  --distinct           Have --all leave out solutions that pass through a board an
+                       earlier one already did
+  --verbose            Report search progress on stderr";
+
+
+/// Nodes `--heuristic auto` gives linear conflict before loading a pattern database.
+pub const QUICK_NODES: u64 = 200_000;
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Algorithm {
+    AStar,
+    IDAStar,
+    ParallelIDAStar,
+    Bidirectional,
+    Weighted,
+    Anytime,
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "astar" => Ok(Algorithm::AStar),
+            "ida" => Ok(Algorithm::IDAStar),
+            "parallel-ida" => Ok(Algorithm::ParallelIDAStar),
+            "bidirectional" => Ok(Algorithm::Bidirectional),
+            "weighted" => Ok(Algorithm::Weighted),
+            "anytime" => Ok(Algorithm::Anytime),
+            _ => Err(format!("unknown algorithm '{}'", s)),
+        }
+    }
+}
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum HeuristicChoice {
+    /// Linear conflict, switching to a pattern database on 4x4 boards it
+    /// doesn't solve within `QUICK_NODES`.
+    Auto,
+    Manhattan,
+    LinearConflict,
+    PatternDatabase,
+}
+
+impl FromStr for HeuristicChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(HeuristicChoice::Auto),
+            "manhattan" => Ok(HeuristicChoice::Manhattan),
+            "linear-conflict" => Ok(HeuristicChoice::LinearConflict),
+            "pdb" => Ok(HeuristicChoice::PatternDatabase),
+            _ => Err(format!("unknown heuristic '{}'", s)),
+        }
+    }
+}
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Scramble {
+    /// `--moves` random moves away from the goal.
+    Walk,
+    /// Any solvable board, all equally likely.
+    Uniform,
+    /// A board whose optimal solution is exactly `--moves` long.
+    Optimal,
+}
+
+impl FromStr for Scramble {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "walk" => Ok(Scramble::Walk),
+            "uniform" => Ok(Scramble::Uniform),
+            "optimal" => Ok(Scramble::Optimal),
+            _ => Err(format!("unknown scramble '{}'", s)),
+        }
+    }
+}
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Format {
+    Text,
+    Json,
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!("unknown format '{}'", s)),
+        }
+    }
+}
+
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Command {
+    Interactive,
+    Help,
+    Solve(String),
+    Play(String),
+    Watch(String),
+    Generate,
+    Verify(String, String),
+    Bench(String),
+    Batch(String),
+    Enumerate,
+}
+
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Options {
+    pub algorithm: Algorithm,
+    pub heuristic: HeuristicChoice,
+    pub format: Format,
+    pub notation: Notation,
+    pub size: Option<(u8, u8)>,
+    pub goal: String,
+    pub weight: f32,
+    pub threads: Option<usize>,
+    pub max_time: Option<Duration>,
+    pub max_nodes: Option<u64>,
+    pub scramble: Scramble,
+    pub moves: Option<usize>,
+    pub count: usize,
+    pub seed: Option<u64>,
+    pub output: Option<String>,
+    pub pdb: Option<String>,
+    pub all: bool,
+    pub max_solutions: Option<usize>,
+    pub distinct: bool,
+    pub verbose: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            algorithm: Algorithm::ParallelIDAStar,
+            heuristic: HeuristicChoice::Auto,
+            format: Format::Text,
+            notation: Notation::Arrows,
+            size: None,
+            goal: String::from("solved"),
+            weight: 2.0,
+            threads: None,
+            max_time: None,
+            max_nodes: None,
+            scramble: Scramble::Walk,
+            moves: None,
+            count: 1,
+            seed: None,
+            output: None,
+            pdb: None,
+            all: false,
+            max_solutions: None,
+            distinct: false,
+            verbose: false,
+        }
+    }
+}
+
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Cli {
+    pub command: Command,
+    pub options: Options,
+}
+
+
+fn parse_value<T: FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid value '{}' for {}", value, flag))
+}
+
+fn parse_size(value: &str) -> Result<(u8, u8), String> {
+    let invalid = || format!("invalid size '{}', expected something like 4x4", value);
+    let (width, height) = value.split_once(['x', 'X']).ok_or_else(invalid)?;
+    let size = (width.parse().map_err(|_| invalid())?, height.parse().map_err(|_| invalid())?);
+    if !Field::is_valid_size(size.0, size.1) { return Err(format!("unsupported board size {}", value)) }
+    Ok(size)
+}
+
+
+/// Parses the arguments after the program name.
+pub fn parse(args: &[String]) -> Result<Cli, String> {
+    let mut options = Options::default();
+    let mut positional: Vec<&str> = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            return Ok(Cli { command: Command::Help, options });
+        }
+        if arg == "-v" || arg == "--verbose" {
+            options.verbose = true;
+            continue;
+        }
+        if arg == "--all" || arg == "--distinct" {
+            if arg == "--all" { options.all = true } else { options.distinct = true }
+            continue;
+        }
+        let Some(flag) = arg.strip_prefix("--") else {
+            positional.push(arg);
+            continue;
+        };
+
+        let (flag, value) = match flag.split_once('=') {
+            Some((flag, value)) => (flag, value),
+            None => (flag, args.next().ok_or_else(|| format!("--{} needs a value", flag))?.as_str()),
+        };
+        let name = format!("--{}", flag);
+        match flag {
+            "algorithm" => options.algorithm = value.parse()?,
+            "heuristic" => options.heuristic = value.parse()?,
+            "format" => options.format = value.parse()?,
+            "notation" => options.notation = value.parse()?,
+            "size" => options.size = Some(parse_size(value)?),
+            "goal" => options.goal = value.to_string(),
+            "weight" => options.weight = parse_value(&name, value)?,
+            "threads" => options.threads = Some(parse_value(&name, value)?),
+            "max-time" => options.max_time = Some(Duration::try_from_secs_f64(parse_value(&name, value)?).map_err(|_| format!("invalid value '{}' for {}", value, name))?),
+            "max-nodes" => options.max_nodes = Some(parse_value(&name, value)?),
+            "scramble" => options.scramble = value.parse()?,
+            "moves" => options.moves = Some(parse_value(&name, value)?),
+            "count" => options.count = parse_value(&name, value)?,
+            "seed" => options.seed = Some(parse_value(&name, value)?),
+            "max-solutions" => options.max_solutions = Some(parse_value(&name, value)?),
+            "output" => options.output = Some(value.to_string()),
+            "pdb" => options.pdb = Some(value.to_string()),
+            _ => return Err(format!("unknown option {}", name)),
+        }
+    }
+
+    let arguments = |count: usize| -> Result<&[&str], String> {
+        if positional.len() != count + 1 {
+            return Err(format!("{} takes {} argument{}", positional[0], count, if count == 1 { "" } else { "s" }));
+        }
+        Ok(&positional[1..])
+    };
+    let command = match positional.first() {
+        None => Command::Interactive,
+        Some(&"help") => Command::Help,
+        Some(&"solve") => Command::Solve(arguments(1)?[0].to_string()),
+        Some(&"play") => Command::Play(arguments(1)?[0].to_string()),
+        Some(&"watch") => Command::Watch(arguments(1)?[0].to_string()),
+        Some(&"generate") => { arguments(0)?; Command::Generate }
+        Some(&"verify") => { let args = arguments(2)?; Command::Verify(args[0].to_string(), args[1].to_string()) }
+        Some(&"bench") => Command::Bench(arguments(1)?[0].to_string()),
+        Some(&"batch") => Command::Batch(arguments(1)?[0].to_string()),
+        Some(&"enumerate") => { arguments(0)?; Command::Enumerate }
+        Some(command) => return Err(format!("unknown command '{}'", command)),
+    };
+    if options.format == Format::Csv && !matches!(command, Command::Batch(_)) {
+        return Err(String::from("--format csv only works with batch"));
+    }
+
+    Ok(Cli { command, options })
+}
+
+
+/// Moves from `start` in `notation`, or in arrows if they can't be made.
+pub fn format_moves(start: &Field, moves: &[Move], notation: Notation) -> String {
+    let moves = MoveSequence::from(moves);
+    moves.format(notation, start).unwrap_or_else(|_| moves.to_string())
+}
+
+
+/// Quotes and escapes `s` as a JSON string.
+pub fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => { let _ = write!(json, "\\u{:04x}", c as u32); }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+
+impl Options {
+    /// Parses a board at `--size`, or a square one going by its length.
+    /// A leading 0x is allowed, like the interactive prompt shows.
+    pub fn board(&self, s: &str) -> Result<Field, String> {
+        let digits = s.trim().trim_start_matches("0x").to_uppercase();
+        let field = match self.size {
+            Some((width, height)) => Field::from_string_sized(width, height, &digits),
+            None => Field::from_string(&digits),
+        };
+        field.map_err(|_| format!("invalid board '{}'", s.trim()))
+    }
+
+    pub fn goal(&self, width: u8, height: u8) -> Result<Field, String> {
+        match self.goal.as_str() {
+            "solved" => Ok(Field::with_size(width, height)),
+            "spiral" => Ok(Field::spiral(width, height)),
+            "blank-first" => Ok(Field::blank_first(width, height)),
+            board => {
+                let goal = Field::from_string_sized(width, height, board.trim_start_matches("0x").to_uppercase())
+                    .map_err(|_| format!("invalid goal '{}' for a {}x{} board", board, width, height))?;
+                Ok(goal)
+            }
+        }
+    }
+
+    pub fn heuristic(&self, goal: &Field) -> Result<HeuristicKind, String> {
+        let (width, height) = (goal.width(), goal.height());
+        let solved = *goal == Field::with_size(width, height);
+        let choice = match self.heuristic {
+            HeuristicChoice::Auto if width == 4 && height == 4 => HeuristicChoice::PatternDatabase,
+            HeuristicChoice::Auto => HeuristicChoice::LinearConflict,
+            choice => choice,
+        };
+
+        Ok(match choice {
+            HeuristicChoice::Manhattan if solved => HeuristicKind::Manhattan,
+            HeuristicChoice::Manhattan => HeuristicKind::GoalManhattan(GoalManhattan::new(goal)),
+            HeuristicChoice::LinearConflict if solved => HeuristicKind::LinearConflict,
+            HeuristicChoice::LinearConflict => HeuristicKind::GoalLinearConflict(GoalLinearConflict::new(goal)),
+            _ => {
+                let partition = pdb::default_partition(width, height);
+                let partition: Vec<&[u8]> = partition.iter().map(Vec::as_slice).collect();
+                if self.verbose { eprintln!("Loading pattern database...") }
+                let pdb = match self.pdb_path(goal, &partition) {
+                    Some(path) => {
+                        if let Some(directory) = path.parent() { let _ = fs::create_dir_all(directory); }
+                        let (pdb, error) = PatternDatabase::load_or_build_for_goal(&path, goal, &partition);
+                        if let Some(error) = error {
+                            eprintln!("warning: couldn't save the pattern database to {}: {}", path.display(), error);
+                        }
+                        pdb
+                    }
+                    None => PatternDatabase::build_for_goal(goal, &partition),
+                };
+                HeuristicKind::PatternDatabase(Arc::new(pdb))
+            }
+        })
+    }
+
+    /// The file pattern databases toward `goal` are kept in: `--pdb`, or one
+    /// named after the size, goal and partition in the user's cache directory.
+    pub fn pdb_path(&self, goal: &Field, partition: &[&[u8]]) -> Option<PathBuf> {
+        if let Some(path) = &self.pdb { return Some(PathBuf::from(path)) }
+        let cache = env::var_os("XDG_CACHE_HOME").map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+            .or_else(|| env::var_os("LOCALAPPDATA").map(PathBuf::from))?;
+        let sizes: Vec<String> = partition.iter().map(|tiles| tiles.len().to_string()).collect();
+        let name = format!("pdb_{}x{}_{}_{}.bin", goal.width(), goal.height(), goal.get_string(), sizes.join("-"));
+        Some(cache.join("pyatnahi").join(name))
+    }
+
+    /// Runs `search` with linear conflict and at most `QUICK_NODES` nodes when
+    /// `--heuristic auto` would otherwise load a pattern database, so boards
+    /// that are easy anyway don't wait for one. `None` if it didn't apply or
+    /// ran out of nodes.
+    fn try_without_pdb<T>(&self, goal: &Field, search: impl FnOnce(HeuristicKind, &SolveOptions) -> Result<T, SolveError>) -> Result<Option<T>, String> {
+        let uses_pdb = self.heuristic == HeuristicChoice::Auto && goal.width() == 4 && goal.height() == 4;
+        // Anytime keeps whatever it has when the nodes run out, which isn't the answer asked for
+        if !uses_pdb || self.algorithm == Algorithm::Anytime || self.max_nodes.is_some_and(|nodes| nodes <= QUICK_NODES) { return Ok(None) }
+
+        let quick = Options { heuristic: HeuristicChoice::LinearConflict, ..self.clone() };
+        let options = SolveOptions { max_nodes: Some(QUICK_NODES), ..self.solve_options() };
+        match search(quick.heuristic(goal)?, &options) {
+            Ok(found) => Ok(Some(found)),
+            Err(SolveError::NodeLimit(_)) => Ok(None),
+            Err(error) => Err(error.to_string()),
+        }
+    }
+
+    pub fn solver(&self, start: &Field, heuristic: HeuristicKind) -> Box<dyn Solver> {
+        match self.algorithm {
+            Algorithm::AStar => Box::new(AStar::with_heuristic(start, heuristic)),
+            Algorithm::IDAStar => Box::new(IDAStar::with_heuristic(start, heuristic)),
+            Algorithm::ParallelIDAStar => {
+                let solver = ParallelIDAStar::with_heuristic(heuristic);
+                Box::new(match self.threads {
+                    Some(threads) => solver.with_threads(threads),
+                    None => solver,
+                })
+            }
+            Algorithm::Bidirectional => Box::new(BidirectionalAStar::with_heuristic(heuristic)),
+            Algorithm::Weighted => Box::new(AStar::with_heuristic(start, heuristic).with_weight(self.weight)),
+            Algorithm::Anytime => Box::new(AnytimeAStar::with_heuristic(start, heuristic, self.weight)),
+        }
+    }
+
+    pub fn solve_options(&self) -> SolveOptions {
+        let mut options = SolveOptions { max_time: self.max_time, max_nodes: self.max_nodes, ..Default::default() };
+        if self.verbose {
+            options.progress = Some(Arc::new(|stats: &SearchStats| {
+                eprintln!("Bound {}: {} nodes expanded in {:.1?}", stats.bound, stats.expanded, stats.elapsed);
+            }));
+        }
+        options
+    }
+}
+
+
+/// Runs every command but `play`, `watch` and the interactive prompts, which need a window.
+pub fn run(cli: &Cli) -> ExitCode {
+    let result = match &cli.command {
+        Command::Solve(board) => solve(board, &cli.options),
+        Command::Generate => generate(&cli.options),
+        Command::Verify(board, moves) => verify(board, moves, &cli.options),
+        Command::Bench(path) => bench(path, &cli.options),
+        Command::Batch(path) => batch(path, &cli.options),
+        Command::Enumerate => enumerate(&cli.options),
+        Command::Help | Command::Interactive | Command::Play(_) | Command::Watch(_) => { println!("{}", USAGE); Ok(true) }
+    };
+
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(error) => {
+            match cli.options.format {
+                Format::Text | Format::Csv => eprintln!("error: {}", error),
+                Format::Json => println!("{{\"error\":{}}}", json_string(&error)),
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+
+/// Solves `start` the way `options` say, toward the goal they pick.
+pub fn solve_board(start: &Field, options: &Options) -> Result<AStarSolution, String> {
+    solve_board_cached(start, options, &mut HashMap::new())
+}
+
+/// `solve_board` that keeps the heuristic for every board size it sees, so a
+/// file of boards loads the pattern database once instead of once a board.
+fn solve_board_cached(start: &Field, options: &Options, heuristics: &mut HashMap<(u8, u8), HeuristicKind>) -> Result<AStarSolution, String> {
+    let goal = options.goal(start.width(), start.height())?;
+    if !start.is_solvable_toward(&goal) { return Err(SolveError::Unsolvable.to_string()) }
+    let quick = options.try_without_pdb(&goal, |heuristic, solve_options| options.solver(start, heuristic).solve(start, solve_options))?;
+    let solution = match quick {
+        Some(solution) => solution,
+        None => {
+            let heuristic = match heuristics.entry((start.width(), start.height())) {
+                Entry::Occupied(entry) => entry.get().clone(),
+                Entry::Vacant(entry) => entry.insert(options.heuristic(&goal)?).clone(),
+            };
+            let mut solver = options.solver(start, heuristic);
+            solver.solve(start, &options.solve_options()).map_err(|error: SolveError| error.to_string())?
+        }
+    };
+    solution.verify(&goal).map_err(|error| format!("the solver's answer is wrong: {}", error))?;
+    Ok(solution)
+}
+
+fn solution_json(board: &Field, solution: &AStarSolution, notation: Notation) -> String {
+    format!(
+        "{{\"board\":{},\"moves\":{},\"length\":{},\"expanded\":{},\"generated\":{},\"time_ms\":{:.3}}}",
+        json_string(&board.get_string()), json_string(&format_moves(board, &solution.moves, notation)), solution.moves.len(),
+        solution.stats.expanded, solution.stats.generated, solution.stats.elapsed.as_secs_f64() * 1000.0,
+    )
+}
+
+pub fn print_solution(board: &Field, solution: &AStarSolution, format: Format, notation: Notation) {
+    match format {
+        Format::Text | Format::Csv => {
+            println!("===========\n{} Moves:", solution.moves.len());
+            println!("{}", format_moves(board, &solution.moves, notation));
+            println!("===========");
+            println!("{} nodes expanded, {} generated in {:.3?}\n", solution.stats.expanded, solution.stats.generated, solution.stats.elapsed);
+        }
+        Format::Json => println!("{}", solution_json(board, solution, notation)),
+    }
+}
+
+fn solve(board: &str, options: &Options) -> Result<bool, String> {
+    let start = options.board(board)?;
+    if options.all { return solve_all(&start, options) }
+    let solution = solve_board(&start, options)?;
+    print_solution(&start, &solution, options.format, options.notation);
+    Ok(true)
+}
+
+fn solve_all(start: &Field, options: &Options) -> Result<bool, String> {
+    let goal = options.goal(start.width(), start.height())?;
+    if !start.is_solvable_toward(&goal) { return Err(SolveError::Unsolvable.to_string()) }
+    let all = AllSolutionsOptions { max_solutions: options.max_solutions, remove_transpositions: options.distinct };
+    let quick = options.try_without_pdb(&goal, |heuristic, solve_options| IDAStar::with_heuristic(start, heuristic).solve_all(start, solve_options, &all))?;
+    let found = match quick {
+        Some(found) => found,
+        None => IDAStar::with_heuristic(start, options.heuristic(&goal)?)
+            .solve_all(start, &options.solve_options(), &all)
+            .map_err(|error| error.to_string())?,
+    };
+
+    let solutions: Vec<String> = found.solutions.iter().map(|moves| format_moves(start, moves, options.notation)).collect();
+    match options.format {
+        Format::Text | Format::Csv => {
+            println!("===========\n{} optimal solution{} of {} moves:", found.count, if found.count == 1 { "" } else { "s" }, found.length);
+            for solution in &solutions {
+                println!("{}", solution);
+            }
+            if (solutions.len() as u64) < found.count { println!("... and {} more", found.count - solutions.len() as u64) }
+            println!("===========");
+            println!("{} nodes expanded, {} generated in {:.3?}\n", found.stats.expanded, found.stats.generated, found.stats.elapsed);
+        }
+        Format::Json => {
+            let solutions: Vec<String> = solutions.iter().map(|solution| json_string(solution)).collect();
+            println!(
+                "{{\"board\":{},\"length\":{},\"count\":{},\"solutions\":[{}],\"expanded\":{},\"generated\":{},\"time_ms\":{:.3}}}",
+                json_string(&start.get_string()), found.length, found.count, solutions.join(","),
+                found.stats.expanded, found.stats.generated, found.stats.elapsed.as_secs_f64() * 1000.0,
+            );
+        }
+    }
+    Ok(true)
+}
+
+
+fn generate(options: &Options) -> Result<bool, String> {
+    let (width, height) = options.size.unwrap_or((4, 4));
+    let goal = options.goal(width, height)?;
+    let seed = options.seed.unwrap_or_else(|| Rng::from_time().next_u64());
+    let mut rng = Rng::new(seed);
+    let moves = match options.scramble {
+        Scramble::Optimal => options.moves.ok_or("--scramble optimal needs --moves")?,
+        _ => options.moves.unwrap_or(200),
+    };
+    if options.scramble == Scramble::Optimal && matches!(options.algorithm, Algorithm::Weighted | Algorithm::Anytime) {
+        return Err(String::from("--scramble optimal needs an algorithm that finds optimal solutions"));
+    }
+    let mut heuristics = HashMap::new();
+
+    for _ in 0..options.count {
+        let board = match options.scramble {
+            Scramble::Walk => scramble::random_walk(&goal, moves, &mut rng),
+            Scramble::Uniform => scramble::uniform(&goal, &mut rng),
+            Scramble::Optimal => scramble::with_optimal_length(&goal, moves, &mut rng, |board| solve_board_cached(board, options, &mut heuristics))?
+                .ok_or_else(|| format!("couldn't find a {}x{} board {} moves from the goal", width, height, moves))?,
+        };
+
+        match options.format {
+            Format::Text | Format::Csv => println!("{}", board.get_string()),
+            Format::Json => {
+                let scramble = match options.scramble {
+                    Scramble::Walk => format!("\"scramble\":\"walk\",\"moves\":{}", moves),
+                    Scramble::Uniform => String::from("\"scramble\":\"uniform\""),
+                    Scramble::Optimal => format!("\"scramble\":\"optimal\",\"length\":{}", moves),
+                };
+                println!("{{\"board\":{},\"seed\":{},{}}}", json_string(&board.get_string()), seed, scramble);
+            }
+        }
+    }
+    Ok(true)
+}
+
+
+fn enumerate(options: &Options) -> Result<bool, String> {
+    let (width, height) = options.size.unwrap_or((3, 3));
+    if width * height > enumerate::MAX_ENUMERABLE_CELLS {
+        return Err(format!("a {}x{} board has too many states, enumerate takes up to {} cells", width, height, enumerate::MAX_ENUMERABLE_CELLS));
+    }
+    let goal = options.goal(width, height)?;
+    let space = StateSpace::explore(&goal);
+
+    match options.format {
+        Format::Text | Format::Csv => {
+            println!("{}x{} toward {}: {} states, the hardest {} moves away", width, height, goal.get_string(), space.states(), space.max_depth());
+            for (depth, count) in space.depth_counts().iter().enumerate() {
+                println!("{:>3} {:>10}", depth, count);
+            }
+            println!("Hardest:");
+            for board in space.hardest() {
+                println!("{}", board.get_string());
+            }
+        }
+        Format::Json => {
+            let depths: Vec<String> = space.depth_counts().iter().map(u64::to_string).collect();
+            let hardest: Vec<String> = space.hardest().iter().map(|board| json_string(&board.get_string())).collect();
+            println!(
+                "{{\"size\":\"{}x{}\",\"goal\":{},\"states\":{},\"max_depth\":{},\"depth_counts\":[{}],\"hardest\":[{}]}}",
+                width, height, json_string(&goal.get_string()), space.states(), space.max_depth(), depths.join(","), hardest.join(","),
+            );
+        }
+    }
+    Ok(true)
+}
+
+
+fn verify(board: &str, moves: &str, options: &Options) -> Result<bool, String> {
+    let start = options.board(board)?;
+    let goal = options.goal(start.width(), start.height())?;
+    let moves = MoveSequence::parse(moves, &start)?.into_moves();
+    let result = solver::verify(&start, &moves, &goal);
+
+    match options.format {
+        Format::Text | Format::Csv => match &result {
+            Ok(()) => println!("Solved in {} moves", moves.len()),
+            Err(error) => println!("Not solved: {}", error),
+        },
+        Format::Json => {
+            let bad_move = result.err().and_then(|error| error.bad_move());
+            let end = result.err().and_then(|error| error.board()).unwrap_or(goal);
+            println!(
+                "{{\"board\":{},\"moves\":{},\"valid\":{},\"solved\":{},\"bad_move\":{},\"final\":{}}}",
+                json_string(&start.get_string()), json_string(&format_moves(&start, &moves, options.notation)), bad_move.is_none(), result.is_ok(),
+                bad_move.map_or(String::from("null"), |index| index.to_string()), json_string(&end.get_string()),
+            );
+        }
+    }
+    Ok(result.is_ok())
+}
+
+
+fn bench(path: &str, options: &Options) -> Result<bool, String> {
+    let contents = if path == "-" { io::read_to_string(io::stdin()) } else { fs::read_to_string(path) }
+        .map_err(|error| format!("couldn't read {}: {}", path, error))?;
+
+    let started = Instant::now();
+    let (mut solved, mut failed, mut expanded) = (0, 0, 0);
+    let mut heuristics = HashMap::new();
+    let mut results = Vec::new();
+    for line in contents.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        let result = options.board(line).and_then(|start| Ok((start, solve_board_cached(&start, options, &mut heuristics)?)));
+        match &result {
+            Ok((start, solution)) => {
+                solved += 1;
+                expanded += solution.stats.expanded;
+                match options.format {
+                    Format::Text | Format::Csv => println!("{} {:>3} moves {:>12} nodes {:>10.3?}", start.get_string(), solution.moves.len(), solution.stats.expanded, solution.stats.elapsed),
+                    Format::Json => results.push(solution_json(start, solution, options.notation)),
+                }
+            }
+            Err(error) => {
+                failed += 1;
+                match options.format {
+                    Format::Text | Format::Csv => println!("{} error: {}", line, error),
+                    Format::Json => results.push(format!("{{\"board\":{},\"error\":{}}}", json_string(line), json_string(error))),
+                }
+            }
+        }
+    }
+
+    let elapsed = started.elapsed();
+    match options.format {
+        Format::Text | Format::Csv => println!("{} solved, {} failed, {} nodes expanded in {:.3?}", solved, failed, expanded, elapsed),
+        Format::Json => println!(
+            "{{\"results\":[{}],\"solved\":{},\"failed\":{},\"expanded\":{},\"time_ms\":{:.3}}}",
+            results.join(","), solved, failed, expanded, elapsed.as_secs_f64() * 1000.0,
+        ),
+    }
+    Ok(failed == 0)
+}
+
+
+/// Quotes a CSV field if it has anything in it that needs quoting.
+pub fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// One line of `batch` output for the board on input line `line`.
+fn batch_record(options: &Options, line: usize, board: &str, result: &Result<(Field, AStarSolution), String>) -> String {
+    let moves = |start: &Field, solution: &AStarSolution| format_moves(start, &solution.moves, options.notation);
+    match (options.format, result) {
+        (Format::Text, Ok((start, solution))) => format!(
+            "{} {:>3} moves {:>12} nodes {:>10.3?}  {}",
+            start.get_string(), solution.moves.len(), solution.stats.expanded, solution.stats.elapsed, moves(start, solution),
+        ),
+        (Format::Text, Err(error)) => format!("{} error: {}", board, error),
+        (Format::Json, Ok((start, solution))) => format!(
+            "{{\"line\":{},\"board\":{},\"moves\":{},\"length\":{},\"expanded\":{},\"time_ms\":{:.3}}}",
+            line, json_string(&start.get_string()), json_string(&moves(start, solution)), solution.moves.len(),
+            solution.stats.expanded, solution.stats.elapsed.as_secs_f64() * 1000.0,
+        ),
+        (Format::Json, Err(error)) => format!("{{\"line\":{},\"board\":{},\"error\":{}}}", line, json_string(board), json_string(error)),
+        (Format::Csv, Ok((start, solution))) => format!(
+            "{},{},{},{},{},{:.3},",
+            line, start.get_string(), csv_field(&moves(start, solution)), solution.moves.len(),
+            solution.stats.expanded, solution.stats.elapsed.as_secs_f64() * 1000.0,
+        ),
+        (Format::Csv, Err(error)) => format!("{},{},,,,,{}", line, csv_field(board), csv_field(error)),
+    }
+}
+
+/// Like `bench`, but writes each result as soon as its board is solved, so it
+/// works on endless input and big datasets. Only the first comma or space
+/// separated field of a line is read as the board, so dataset files with more
+/// columns work as they are.
+fn batch(path: &str, options: &Options) -> Result<bool, String> {
+    let input: Box<dyn BufRead> = if path == "-" {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(BufReader::new(File::open(path).map_err(|error| format!("couldn't read {}: {}", path, error))?))
+    };
+    let output_name = options.output.as_deref().unwrap_or("stdout");
+    let mut output: Box<dyn Write> = match &options.output {
+        Some(output) => Box::new(BufWriter::new(File::create(output).map_err(|error| format!("couldn't create {}: {}", output, error))?)),
+        None => Box::new(io::stdout().lock()),
+    };
+    let write_error = |error: io::Error| format!("couldn't write to {}: {}", output_name, error);
+
+    if options.format == Format::Csv {
+        writeln!(output, "line,board,moves,length,expanded,time_ms,error").map_err(write_error)?;
+    }
+
+    let started = Instant::now();
+    let (mut solved, mut failed) = (0, 0);
+    let mut heuristics = HashMap::new();
+    for (index, line) in input.lines().enumerate() {
+        let line = line.map_err(|error| format!("couldn't read {}: {}", path, error))?;
+        let Some(board) = line.split([',', ' ', '\t']).map(str::trim).find(|field| !field.is_empty()) else { continue };
+        if board.starts_with('#') { continue }
+
+        let result = options.board(board).and_then(|start| Ok((start, solve_board_cached(&start, options, &mut heuristics)?)));
+        if result.is_ok() { solved += 1 } else { failed += 1 }
+        writeln!(output, "{}", batch_record(options, index + 1, board, &result)).map_err(write_error)?;
+        output.flush().map_err(write_error)?;
+    }
+
+    eprintln!("{} solved, {} failed in {:.3?}", solved, failed, started.elapsed());
+    Ok(failed == 0)
+}
@@ -0,0 +1,100 @@
+#[cfg(test)]
+use super::*;
+#[cfg(test)]
+use pyatnahi::{pdb, scramble};
+
+
+
+#[test]
+fn test_cli_parse() {
+    use cli::{Algorithm, Command, Format, HeuristicChoice};
+    use std::time::Duration;
+
+    let parse = |line: &str| cli::parse(&line.split_whitespace().map(String::from).collect::<Vec<String>>());
+
+    assert_eq!(parse("").unwrap().command, Command::Interactive);
+    assert_eq!(parse("--help").unwrap().command, Command::Help);
+    assert_eq!(parse("generate --seed 5").unwrap().options.seed, Some(5));
+    assert_eq!(parse("verify 123456789ABCDEF0 ^<").unwrap().command, Command::Verify("123456789ABCDEF0".into(), "^<".into()));
+    assert_eq!(parse("watch 123045 --size 3x2").unwrap().command, Command::Watch("123045".into()));
+    assert_eq!(parse("solve 123 --pdb tables.bin").unwrap().options.pdb.as_deref(), Some("tables.bin"));
+
+    let solve = parse("solve 51247308A6BE9FCD --algorithm=astar --heuristic manhattan --format json --size 4x4 --max-time 1.5 -v").unwrap();
+    assert_eq!(solve.command, Command::Solve("51247308A6BE9FCD".into()));
+    assert_eq!(solve.options.algorithm, Algorithm::AStar);
+    assert_eq!(solve.options.heuristic, HeuristicChoice::Manhattan);
+    assert_eq!(solve.options.format, Format::Json);
+    assert_eq!(solve.options.size, Some((4, 4)));
+    assert_eq!(solve.options.max_time, Some(Duration::from_millis(1500)));
+    assert!(solve.options.verbose);
+
+    for bad in ["solve", "solve a b", "frob", "solve 1 --algorithm dfs", "solve 1 --size 9x9", "solve 1 --weight", "bench x --nope 1"] {
+        assert!(parse(bad).is_err(), "{}", bad);
+    }
+
+    let options = parse("solve x --size 2x3 --goal blank-first").unwrap().options;
+    assert!(options.board("0x123450").unwrap() == field::Field::from_string_sized(2, 3, "123450").unwrap());
+    assert!(options.board("1234").is_err());
+    assert!(options.goal(2, 3).unwrap() == field::Field::blank_first(2, 3));
+
+    assert_eq!(cli::json_string("a\"b\\c\n\u{1}"), "\"a\\\"b\\\\c\\n\\u0001\"");
+}
+
+
+
+#[test]
+fn test_cli_solve_board() {
+    let field = field::Field::from(0x51247308A6BE9FCD).ok().unwrap();
+    for algorithm in ["astar", "ida", "parallel-ida", "bidirectional"] {
+        let args: Vec<String> = ["solve", "x", "--heuristic", "linear-conflict", "--algorithm", algorithm].iter().map(|s| s.to_string()).collect();
+        let options = cli::parse(&args).unwrap().options;
+        assert_eq!(cli::solve_board(&field, &options).unwrap().moves.len(), 27, "{}", algorithm);
+    }
+
+    let unsolvable = field::Field::from(0x123456789AFB0EDC).ok().unwrap();
+    assert!(cli::solve_board(&unsolvable, &cli::Options::default()).is_err());
+
+    let mut rng = scramble::Rng::new(42);
+    let goal = field::Field::spiral(3, 3);
+    let start = scramble::random_walk(&goal, 50, &mut rng);
+    assert!(start.is_solvable_toward(&goal));
+    assert!(scramble::random_walk(&goal, 50, &mut scramble::Rng::new(42)) == start);
+    let options = cli::Options { goal: "spiral".into(), ..Default::default() };
+    assert!(*cli::solve_board(&start, &options).unwrap().states.last().unwrap() == goal);
+
+    // Easy 4x4 boards don't need the pattern database, so it isn't built or saved
+    let path = std::env::temp_dir().join(format!("pyatnahi_cli_pdb_{}.bin", std::process::id()));
+    let options = cli::Options { pdb: Some(path.to_string_lossy().into_owned()), ..Default::default() };
+    assert_eq!(cli::solve_board(&field, &options).unwrap().moves.len(), 27);
+    assert!(!path.exists());
+    let partition = pdb::default_partition(4, 4);
+    let partition: Vec<&[u8]> = partition.iter().map(Vec::as_slice).collect();
+    assert_eq!(options.pdb_path(&field::Field::with_size(4, 4), &partition), Some(path));
+    let cached = cli::Options::default().pdb_path(&field::Field::with_size(4, 4), &partition);
+    assert!(cached.is_none_or(|path| path.ends_with("pyatnahi/pdb_4x4_123456789ABCDEF0_6-6-3.bin")));
+
+    // Nor does a pattern database that can't be saved stop the solve
+    let blocker = std::env::temp_dir().join(format!("pyatnahi_cli_blocker_{}", std::process::id()));
+    std::fs::write(&blocker, "").unwrap();
+    let options = cli::Options { heuristic: cli::HeuristicChoice::PatternDatabase, pdb: Some(blocker.join("pdb.bin").to_string_lossy().into_owned()), ..Default::default() };
+    let start = field::Field::from_string("867254301").unwrap();
+    assert_eq!(cli::solve_board(&start, &options).unwrap().moves.len(), 31);
+    std::fs::remove_file(&blocker).unwrap();
+}
+
+
+
+#[test]
+fn test_cli_batch_format() {
+    let args: Vec<String> = ["batch", "-", "--format", "csv", "--output", "out.csv"].iter().map(|s| s.to_string()).collect();
+    let cli = cli::parse(&args).unwrap();
+    assert_eq!(cli.command, cli::Command::Batch("-".into()));
+    assert_eq!(cli.options.format, cli::Format::Csv);
+    assert_eq!(cli.options.output.as_deref(), Some("out.csv"));
+
+    let args: Vec<String> = ["solve", "x", "--format", "csv"].iter().map(|s| s.to_string()).collect();
+    assert!(cli::parse(&args).is_err());
+
+    assert_eq!(cli::csv_field("^v<>"), "^v<>");
+    assert_eq!(cli::csv_field("a, \"b\""), "\"a, \"\"b\"\"\"");
+}
@@ -0,0 +1,92 @@
+use crate::field::{Field, Move};
+use crate::rank;
+
+
+/// Largest board `StateSpace` takes on. 12 cells is 12! bits, 60 MB of them.
+pub const MAX_ENUMERABLE_CELLS: u8 = 12;
+
+
+/// Every board that can reach a goal, found breadth first from the goal, so
+/// each one is seen at its exact optimal distance. The visited table is one
+/// bit per Myrvold–Ruskey rank.
+pub struct StateSpace {
+    goal: Field,
+    depth_counts: Vec<u64>,
+    hardest: Vec<Field>,
+}
+
+
+impl StateSpace {
+    pub fn explore(goal: &Field) -> StateSpace {
+        StateSpace::explore_with(goal, |_, _| {})
+    }
+
+    /// Same as `explore`, also calling `visit` with every board and its
+    /// distance from the goal, handy for checking heuristics and solvers.
+    pub fn explore_with(goal: &Field, mut visit: impl FnMut(&Field, usize)) -> StateSpace {
+        let (width, height, cells) = (goal.width(), goal.height(), goal.cell_count());
+        assert!(cells <= MAX_ENUMERABLE_CELLS, "a {}x{} board has too many states to enumerate", width, height);
+
+        let size = rank::placements(cells as usize, cells as usize) as usize;
+        let mut visited = vec![0_u64; size.div_ceil(64)];
+        let mut visit_rank = |rank: u32| -> bool {
+            let (word, bit) = (rank as usize / 64, rank % 64);
+            let seen = visited[word] & (1 << bit) != 0;
+            visited[word] |= 1 << bit;
+            !seen
+        };
+
+        visit_rank(goal.mr_rank() as u32);
+        // Boards at the current depth, packed. Up to 12 cells take 48 bits
+        let mut frontier = vec![goal.unique_id() as u64];
+        let mut depth_counts = Vec::new();
+        loop {
+            depth_counts.push(frontier.len() as u64);
+            let depth = depth_counts.len() - 1;
+
+            let mut next = Vec::new();
+            for &state in &frontier {
+                let field = Field::from_packed(width, height, state as u128).unwrap();
+                visit(&field, depth);
+                for m in Move::iter() {
+                    let mut neighbour = field;
+                    if !neighbour.make_move(m) { continue }
+                    if visit_rank(neighbour.mr_rank() as u32) { next.push(neighbour.unique_id() as u64) }
+                }
+            }
+
+            if next.is_empty() { break }
+            frontier = next;
+        }
+
+        let mut hardest: Vec<Field> = frontier.iter()
+            .map(|&state| Field::from_packed(width, height, state as u128).unwrap())
+            .collect();
+        hardest.sort_by_key(Field::get_string);
+        StateSpace { goal: *goal, depth_counts, hardest }
+    }
+
+
+    pub fn goal(&self) -> &Field {
+        &self.goal
+    }
+
+    /// How many boards sit at each distance from the goal, starting at 0.
+    pub fn depth_counts(&self) -> &[u64] {
+        &self.depth_counts
+    }
+
+    pub fn states(&self) -> u64 {
+        self.depth_counts.iter().sum()
+    }
+
+    /// Most moves any board needs to reach the goal.
+    pub fn max_depth(&self) -> usize {
+        self.depth_counts.len() - 1
+    }
+
+    /// Every board `max_depth` moves away, sorted.
+    pub fn hardest(&self) -> &[Field] {
+        &self.hardest
+    }
+}
@@ -1,10 +1,9 @@
 use std::hash::{Hash, Hasher};
 use std::fmt;
-use raylib::prelude::*;
+use crate::rank;
 
-#[allow(unused)]
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum Move {
     Up,
     Down,
@@ -12,7 +11,6 @@ pub enum Move {
     Right,
 }
 
-#[allow(unused)]
 impl Move {
     pub fn iter() -> impl Iterator<Item = Move> {
         [Move::Up, Move::Down, Move::Left, Move::Right].iter().copied()
@@ -41,89 +39,192 @@ impl fmt::Display for Move {
 }
 
 
+pub const MAX_CELLS: usize = 25;
+
+
 #[derive(Clone, Copy)]
 pub struct Field {
-    data: u64,
+    data: u128,
+    width: u8,
+    height: u8,
     empty_index: u8
 }
 
 
-#[allow(unused)]
 impl Field {
     pub fn new() -> Field {
-        Field {
-            data: 0x123456789ABCDEF0,
-            empty_index: 15,
+        Field::with_size(4, 4)
+    }
+
+    pub fn with_size(width: u8, height: u8) -> Field {
+        assert!(Field::is_valid_size(width, height), "unsupported board size {}x{}", width, height);
+        let cells = width * height;
+        let mut field = Field { data: 0, width, height, empty_index: cells - 1 };
+        for i in 0..cells - 1 {
+            field.set_linear(i, i + 1);
         }
+        field
     }
 
-    pub fn from(state: u64) -> Result<Field, ()> {
-        let mut empty_index_opt = None;
-        for i in 0..16 {
-            if (state >> 4 * (15 - i)) & 0xF == 0 { empty_index_opt = Some(i); break; }
+    /// Goal layout with the blank in the top left corner and the tiles after it.
+    pub fn blank_first(width: u8, height: u8) -> Field {
+        let mut field = Field::with_size(width, height);
+        for i in 0..field.cell_count() {
+            field.set_linear(i, i);
+        }
+        field.empty_index = 0;
+        field
+    }
+
+    /// Goal layout with the tiles laid out clockwise from the top left corner,
+    /// spiralling inwards and ending with the blank.
+    pub fn spiral(width: u8, height: u8) -> Field {
+        let mut field = Field::with_size(width, height);
+        let (mut left, mut top, mut right, mut bottom) = (0, 0, width - 1, height - 1);
+        let mut order = Vec::with_capacity(field.cell_count() as usize);
+        while left <= right && top <= bottom {
+            order.extend((left..=right).map(|column| top * width + column));
+            order.extend((top + 1..=bottom).map(|row| row * width + right));
+            if top < bottom { order.extend((left..right).rev().map(|column| bottom * width + column)) }
+            if left < right { order.extend((top + 1..bottom).rev().map(|row| row * width + left)) }
+            left += 1;
+            top += 1;
+            if right == 0 || bottom == 0 { break }
+            right -= 1;
+            bottom -= 1;
+        }
+
+        for (tile, &index) in order.iter().enumerate() {
+            field.set_linear(index, (tile as u8 + 1) % field.cell_count());
         }
+        field.empty_index = *order.last().unwrap();
+        field
+    }
+
+    pub fn is_valid_size(width: u8, height: u8) -> bool {
+        width >= 2 && height >= 2 && (width as usize) * (height as usize) <= MAX_CELLS
+    }
+
+    pub fn from(state: u64) -> Result<Field, ()> {
+        Field::from_packed(4, 4, state as u128)
+    }
+
+    pub fn from_packed(width: u8, height: u8, state: u128) -> Result<Field, ()> {
+        if !Field::is_valid_size(width, height) { return Err(()) }
+        let mut field = Field { data: state, width, height, empty_index: 0 };
+        if state >> (field.cell_bits() * field.cell_count()) != 0 { return Err(()) }
 
-        match empty_index_opt {
-            Some(empty_index) => { Ok(Field {
-                data: state,
-                empty_index: empty_index,
-            }) },
+        match (0..width * height).find(|&i| field.get_linear(i) == 0) {
+            Some(empty_index) => {
+                field.empty_index = empty_index;
+                Ok(field)
+            },
 
             None => { Err(()) }
         }
     }
     
 
+    /// Board from its cells in row order, 0 being the blank.
+    pub fn from_cells(width: u8, height: u8, cells: &[u8]) -> Result<Field, ()> {
+        if !Field::is_valid_size(width, height) || cells.len() != (width * height) as usize { return Err(()) }
+        let mut found = [false; MAX_CELLS];
+        let mut field = Field::with_size(width, height);
+        for (i, &value) in cells.iter().enumerate() {
+            if value as usize >= cells.len() || found[value as usize] { return Err(()) }
+            found[value as usize] = true;
+            field.set_linear(i as u8, value);
+            if value == 0 { field.empty_index = i as u8 }
+        }
+        Ok(field)
+    }
+
+    pub fn cells(&self) -> Vec<u8> {
+        (0..self.cell_count()).map(|i| self.get_linear(i)).collect()
+    }
+
     pub fn from_string<S: AsRef<str>>(s: S) -> Result<Field, ()> {
-        let mut f = Field::new();
+        let len = s.as_ref().len();
+        let side = (2..=5).find(|side| side * side == len).ok_or(())?;
+        Field::from_string_sized(side as u8, side as u8, s)
+    }
+
+    pub fn from_string_sized<S: AsRef<str>>(width: u8, height: u8, s: S) -> Result<Field, ()> {
+        if !Field::is_valid_size(width, height) { return Err(()) }
+        let mut f = Field::with_size(width, height);
         f.set_string(s)
     }
     
 
     pub fn set_string<S: AsRef<str>>(&mut self, s: S) -> Result<Field, ()> {
         let bytes = s.as_ref().as_bytes();
-        if bytes.len() != 16 { return Err(()); }
-        let mut found = [false; 16];
-        let mut newdata = 0x0;
-        for i in 0_u8..16_u8 {
-            let mut value = bytes[i as usize];
-            if value == b'0' {
-                self.empty_index = i;
-            }
-
-            if value >= b'0' && value <= b'9' {
-                value -= b'0';
-            } else if value >= b'A' && value <= b'F' {
-                value -= b'A';
-                value += 10;
-            } else {
-                return Err(());
+        let cells = self.cell_count() as usize;
+        if bytes.len() != cells { return Err(()); }
+        let mut found = [false; MAX_CELLS];
+        let mut field = *self;
+        field.data = 0;
+        for (i, &byte) in bytes.iter().enumerate() {
+            let value = match byte {
+                b'0'..=b'9' => byte - b'0',
+                b'A'..=b'Z' => byte - b'A' + 10,
+                _ => return Err(()),
+            };
+            if value as usize >= cells || found[value as usize] { return Err(()); }
+
+            if value == 0 {
+                field.empty_index = i as u8;
             }
-
-            newdata |= (value as u64) << 4 * (15 - i);
+            field.set_linear(i as u8, value);
             found[value as usize] = true;
         }
 
-        let success = (0..16).all(|i| {found[i]});
-        if !success { return Err(()); }
-
-        self.data = newdata;
+        *self = field;
         Ok(*self)
     }
 
 
     pub fn get_string(&self) -> String {
-        let mut s = String::with_capacity(16);
-        (0..15).for_each(|i| {
-            let v = (self.data >> 4 * (15 - i)) as u8;
-            s.push((if v < 10 { b'0' + v } else { b'A' + (v - 10) }) as char);
-        });
-        s
+        (0..self.cell_count()).map(|i| {
+            let v = self.get_linear(i);
+            (if v < 10 { b'0' + v } else { b'A' + (v - 10) }) as char
+        }).collect()
+    }
+
+
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+
+    pub fn cell_count(&self) -> u8 {
+        self.width * self.height
+    }
+
+
+    fn cell_bits(&self) -> u8 {
+        if self.cell_count() <= 16 { 4 } else { 5 }
+    }
+
+    fn cell_shift(&self, i: u8) -> u8 {
+        self.cell_bits() * (self.cell_count() - 1 - i)
+    }
+
+    fn cell_mask(&self) -> u128 {
+        (1 << self.cell_bits()) - 1
     }
 
 
     pub fn get_linear<I: Into<u8>>(&self, i: I) -> u8 {
-        ((self.data >> 4 * (15 - i.into())) & 0xF) as u8
+        ((self.data >> self.cell_shift(i.into())) & self.cell_mask()) as u8
+    }
+
+    fn set_linear(&mut self, i: u8, value: u8) {
+        let shift = self.cell_shift(i);
+        self.data &= !(self.cell_mask() << shift);
+        self.data |= (value as u128) << shift;
     }
 
 
@@ -132,41 +233,49 @@ impl Field {
     }
 
 
-    pub fn is_solvable(&self) -> bool {
-        let mut sum: u8 = 0;
-        for i in 0_u8..16_u8 {
+    fn parity(&self) -> bool {
+        let cells = self.cell_count();
+        let mut inversions: u32 = 0;
+        for i in 0..cells {
             let value_i = self.get_linear(i);
-            if value_i == 0 {
-                sum += i / 4;
-                continue;
-            }
-            for j in 0_u8..(i+1) {
+            if value_i == 0 { continue }
+            for j in (i + 1)..cells {
                 let value_j = self.get_linear(j);
-                if value_j == 0 { continue }
-                if value_j < value_i { sum += 1 }
+                if value_j != 0 && value_j < value_i { inversions += 1 }
             }
         }
 
-        return sum % 2 == 0;
+        // On even widths a vertical move shifts a tile by an odd number of places,
+        // so the blank's row has to be counted in as well.
+        if self.width.is_multiple_of(2) {
+            inversions += (self.empty_index / self.width) as u32;
+        }
+
+        inversions.is_multiple_of(2)
+    }
+
+    pub fn is_solvable(&self) -> bool {
+        self.is_solvable_toward(&Field::with_size(self.width, self.height))
+    }
+
+    /// Whether `goal` can be reached from here: same size and same parity.
+    pub fn is_solvable_toward(&self, goal: &Field) -> bool {
+        self.width == goal.width && self.height == goal.height && self.parity() == goal.parity()
     }
 
 
     pub fn is_solved(&self) -> bool {
-        self.data == 0x123456789ABCDEF0
+        self.empty_index == self.cell_count() - 1
+            && (0..self.cell_count() - 1).all(|i| self.get_linear(i) == i + 1)
     }
 
 
     fn swap<I: Into<u8>>(&mut self, i1: I, i2: I) {
-        let shift1 = 4 * (15 - i1.into());
-        let shift2 = 4 * (15 - i2.into());
-        let mask1 = 0xF << shift1;
-        let mask2 = 0xF << shift2;
-        let bits1 = self.data & mask1;
-        let bits2 = self.data & mask2;
-        self.data &= !mask1;
-        self.data &= !mask2;
-        self.data |= (bits1 >> shift1) << shift2;
-        self.data |= (bits2 >> shift2) << shift1;
+        let (i1, i2) = (i1.into(), i2.into());
+        let value1 = self.get_linear(i1);
+        let value2 = self.get_linear(i2);
+        self.set_linear(i1, value2);
+        self.set_linear(i2, value1);
     }
 
 
@@ -179,53 +288,148 @@ impl Field {
 
     pub fn is_valid_move(&self, m: Move) -> bool {
         match m {
-            Move::Up => { self.empty_index > 3 }
-            Move::Down => { self.empty_index < 12 }
-            Move::Left => { self.empty_index % 4 > 0 }
-            Move::Right => { self.empty_index % 4 < 3 }
+            Move::Up => { self.empty_index >= self.width }
+            Move::Down => { self.empty_index < self.cell_count() - self.width }
+            Move::Left => { !self.empty_index.is_multiple_of(self.width) }
+            Move::Right => { self.empty_index % self.width < self.width - 1 }
         }
     }
 
 
+    /// Index of the tile that slides into the blank on `m`, if the move is valid.
+    pub fn moved_piece_index(&self, m: Move) -> Option<u8> {
+        if !self.is_valid_move(m) { return None }
+        Some(match m {
+            Move::Up => { self.empty_index - self.width }
+            Move::Down => { self.empty_index + self.width }
+            Move::Left => { self.empty_index - 1 }
+            Move::Right => { self.empty_index + 1 }
+        })
+    }
+
+
     pub fn up(&mut self) -> bool {
-        if !self.is_valid_move(Move::Up) { return false }
-        self.swap_with_empty(self.empty_index - 4)
+        self.make_move(Move::Up)
     }
 
     pub fn down(&mut self) -> bool {
-        if !self.is_valid_move(Move::Down) { return false }
-        self.swap_with_empty(self.empty_index + 4)
+        self.make_move(Move::Down)
     }
 
     pub fn left(&mut self) -> bool {
-        if !self.is_valid_move(Move::Left) { return false }
-        self.swap_with_empty(self.empty_index - 1)
+        self.make_move(Move::Left)
     }
 
     pub fn right(&mut self) -> bool {
-        if !self.is_valid_move(Move::Right) { return false }
-        self.swap_with_empty(self.empty_index + 1)
+        self.make_move(Move::Right)
     }
 
 
     pub fn make_move(&mut self, m: Move) -> bool {
-        match m {
-            Move::Up    => { self.up()      },
-            Move::Down  => { self.down()    },
-            Move::Left  => { self.left()    },
-            Move::Right => { self.right()   },
+        match self.moved_piece_index(m) {
+            Some(index) => { self.swap_with_empty(index) },
+            None => { false },
         }
     }
 
 
-    pub fn unique_id(&self) -> u64 {
-        return self.data;
+    /// Blank moves that slide `cell` and every tile between it and the blank
+    /// one step toward the blank, like pushing a line of tiles on the real
+    /// puzzle. `None` unless `cell` shares a row or a column with the blank.
+    pub fn line_moves(&self, cell: u8) -> Option<Vec<Move>> {
+        if cell >= self.cell_count() || cell == self.empty_index { return None }
+        let (row, column) = (cell / self.width, cell % self.width);
+        let (empty_row, empty_column) = (self.empty_index / self.width, self.empty_index % self.width);
+
+        let (m, count) = if row == empty_row {
+            if column < empty_column { (Move::Left, empty_column - column) } else { (Move::Right, column - empty_column) }
+        } else if column == empty_column {
+            if row < empty_row { (Move::Up, empty_row - row) } else { (Move::Down, row - empty_row) }
+        } else {
+            return None;
+        };
+        Some(vec![m; count as usize])
+    }
+
+    /// Makes the `line_moves` for `cell`, leaving the blank where `cell` was.
+    pub fn shift_line(&mut self, cell: u8) -> Option<Vec<Move>> {
+        let moves = self.line_moves(cell)?;
+        for &m in &moves {
+            self.make_move(m);
+        }
+        Some(moves)
+    }
+
+
+    pub fn unique_id(&self) -> u128 {
+        self.data
+    }
+
+
+    /// Cell of every tile, the blank's first.
+    fn all_positions(&self) -> [u8; MAX_CELLS] {
+        let mut positions = [0_u8; MAX_CELLS];
+        for i in 0..self.cell_count() {
+            positions[self.get_linear(i) as usize] = i;
+        }
+        positions
+    }
+
+    /// Cells `tiles` are on, in the same order.
+    pub fn tile_positions(&self, tiles: &[u8]) -> Vec<u8> {
+        let positions = self.all_positions();
+        tiles.iter().map(|&tile| positions[tile as usize]).collect()
+    }
+
+    fn from_positions(width: u8, height: u8, positions: &[u8]) -> Field {
+        let mut cells = vec![0_u8; positions.len()];
+        for (tile, &p) in positions.iter().enumerate() {
+            cells[p as usize] = tile as u8;
+        }
+        Field::from_cells(width, height, &cells).unwrap()
+    }
+
+    /// Dense index in `0..cells!`: the lexicographic rank of where each tile
+    /// is, the blank first. Unlike `unique_id` it can index an array directly.
+    pub fn lex_rank(&self) -> u128 {
+        rank::lex_rank(&self.all_positions()[..self.cell_count() as usize], self.cell_count())
+    }
+
+    pub fn from_lex_rank(width: u8, height: u8, rank: u128) -> Result<Field, ()> {
+        let cells = width as usize * height as usize;
+        if !Field::is_valid_size(width, height) || rank >= rank::placements(cells, cells) { return Err(()) }
+        Ok(Field::from_positions(width, height, &rank::lex_unrank(rank, cells as u8, cells)))
+    }
+
+    /// Like `lex_rank`, in Myrvold–Ruskey order, which is quicker to compute.
+    pub fn mr_rank(&self) -> u128 {
+        rank::mr_rank(&self.all_positions()[..self.cell_count() as usize], self.cell_count())
+    }
+
+    pub fn from_mr_rank(width: u8, height: u8, rank: u128) -> Result<Field, ()> {
+        let cells = width as usize * height as usize;
+        if !Field::is_valid_size(width, height) || rank >= rank::placements(cells, cells) { return Err(()) }
+        Ok(Field::from_positions(width, height, &rank::mr_unrank(rank, cells as u8, cells)))
+    }
+
+    /// Lexicographic rank of just where `tiles` are, in `0..placements(cells, tiles.len())`.
+    /// Unrank with `rank::lex_unrank` to get the positions back.
+    pub fn partial_lex_rank(&self, tiles: &[u8]) -> u128 {
+        rank::lex_rank(&self.tile_positions(tiles), self.cell_count())
+    }
+
+    pub fn partial_mr_rank(&self, tiles: &[u8]) -> u128 {
+        rank::mr_rank(&self.tile_positions(tiles), self.cell_count())
     }
 
 
     pub fn display(&self) {
-        (0..13).step_by(4).for_each(|i| {
-            println!("{:>4} {:>4} {:>4} {:>4}", self.get_linear(i), self.get_linear(i + 1), self.get_linear(i + 2), self.get_linear(i + 3));
+        let cell_width = if self.cell_count() > 10 { 4 } else { 3 };
+        (0..self.height).for_each(|row| {
+            let line: Vec<String> = (0..self.width)
+                .map(|column| format!("{:>cell_width$}", self.get_linear(row * self.width + column)))
+                .collect();
+            println!("{}", line.join(" "));
         });
     }
 }
@@ -240,32 +444,13 @@ impl Default for Field {
 
 impl PartialEq for Field{
     fn eq(&self, other: &Self) -> bool {
-        self.data == other.data
+        self.data == other.data && self.width == other.width && self.height == other.height
     }
 }
 
 
 impl Hash for Field {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        state.write_u64(self.data);
-    }
-}
-
-
-pub trait Drawable {
-    fn draw(&self, gfx: RaylibDrawHandle<'_>);
-}
-
-impl Drawable for Field {
-    fn draw(&self, mut gfx: RaylibDrawHandle<'_>) {
-        for i in 0_u8..16_u8 {
-            let value = self.get_linear(i);
-            if value == 0 { continue }
-
-            let text = format!("{}", value);
-            let x = 20 + 64 * ((i as i32) % 4);
-            let y = 20 + 64 * ((i as i32) / 4);
-            gfx.draw_text(text.as_str(), x, y, 32, Color::BLACK);
-        }
+        state.write_u128(self.data);
     }
 }
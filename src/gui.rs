@@ -0,0 +1,562 @@
+//! The raylib window for playing a board by hand. Only built with the `gui` feature.
+
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+use raylib::prelude::*;
+use raylib::prelude::RaylibDraw;
+use crate::astar::{AStar, AStarSolution, IDAStar};
+use crate::field::{Field, Move};
+use crate::heuristic::GoalLinearConflict;
+use crate::session::{self, Session};
+use crate::solver::{CancelToken, SolveOptions, Solver};
+
+
+/// Side of a cell on screen, in pixels.
+pub const CELL_SIZE: i32 = 64;
+/// Where a tile's number sits inside its cell.
+const TEXT_OFFSET: i32 = 20;
+/// How far a pointer has to travel before it counts as a drag instead of a click.
+const DRAG_THRESHOLD: f32 = CELL_SIZE as f32 / 4.0;
+/// Height of the bar under the board that playback writes its counter in.
+const STATUS_HEIGHT: i32 = 32;
+/// How long a hint looks for the optimal move before settling for a good one.
+pub const HINT_TIME: Duration = Duration::from_secs(1);
+/// Weight of the A* a hint falls back on.
+const HINT_FALLBACK_WEIGHT: f32 = 3.0;
+/// How long the fallback gets before the hint gives up.
+const HINT_FALLBACK_TIME: Duration = Duration::from_secs(5);
+/// How much the fallback's open and closed sets may take before the hint gives up.
+const HINT_FALLBACK_MEMORY: usize = 256 << 20;
+/// How long the start board's optimal length is looked for, for the victory screen.
+const OPTIMAL_TIME: Duration = Duration::from_secs(30);
+
+
+/// The board cell under screen point (`x`, `y`), the inverse of where `draw` puts things.
+pub fn cell_at(field: &Field, x: f32, y: f32) -> Option<u8> {
+    if x < 0.0 || y < 0.0 { return None }
+    let (column, row) = ((x as i32 / CELL_SIZE) as u8, (y as i32 / CELL_SIZE) as u8);
+    if column >= field.width() || row >= field.height() { return None }
+    Some(row * field.width() + column)
+}
+
+
+pub trait Drawable {
+    fn draw(&self, gfx: RaylibDrawHandle<'_>);
+}
+
+impl Drawable for Field {
+    fn draw(&self, mut gfx: RaylibDrawHandle<'_>) {
+        for i in 0..self.cell_count() {
+            let value = self.get_linear(i);
+            if value == 0 { continue }
+
+            let x = CELL_SIZE * ((i % self.width()) as i32);
+            let y = CELL_SIZE * ((i / self.width()) as i32);
+            draw_tile(&mut gfx, value, x, y);
+        }
+    }
+}
+
+/// Draws tile `value` in the cell whose top left corner is at (`x`, `y`).
+fn draw_tile(gfx: &mut RaylibDrawHandle<'_>, value: u8, x: i32, y: i32) {
+    let text = format!("{}", value);
+    gfx.draw_text(text.as_str(), x + TEXT_OFFSET, y + TEXT_OFFSET, 32, Color::BLACK);
+}
+
+
+/// A way to the goal from `board`, for telling the player what to move.
+#[derive(Clone, PartialEq)]
+pub struct Hint {
+    pub board: Field,
+    pub moves: Vec<Move>,
+    /// Whether `moves` is known to be as short as it gets. Hard boards get
+    /// a weighted A* solution instead, which usually isn't.
+    pub optimal: bool,
+}
+
+impl Hint {
+    /// Solves `board` toward `goal`, optimally if IDA* manages in `time`,
+    /// otherwise with weighted A*. `None` if it's unsolvable, `cancel` went
+    /// off first or the fallback ran out of time or memory.
+    pub fn find(board: &Field, goal: &Field, time: Duration, cancel: &CancelToken) -> Option<Hint> {
+        let heuristic = GoalLinearConflict::new(goal);
+        let options = SolveOptions { max_time: Some(time), cancel: Some(cancel.clone()), ..Default::default() };
+        if let Ok(solution) = IDAStar::with_heuristic(board, heuristic).solve(board, &options) {
+            return Some(Hint { board: *board, moves: solution.moves, optimal: true });
+        }
+
+        let options = SolveOptions {
+            max_time: Some(HINT_FALLBACK_TIME),
+            max_memory: Some(HINT_FALLBACK_MEMORY),
+            cancel: Some(cancel.clone()),
+            ..Default::default()
+        };
+        let solution = AStar::with_heuristic(board, heuristic).with_weight(HINT_FALLBACK_WEIGHT).solve(board, &options).ok()?;
+        Some(Hint { board: *board, moves: solution.moves, optimal: false })
+    }
+
+    /// The tile to slide next, `None` once solved.
+    pub fn next_tile(&self) -> Option<u8> {
+        self.board.moved_piece_index(*self.moves.first()?)
+    }
+
+    /// Moves left to the goal.
+    pub fn distance(&self) -> usize {
+        self.moves.len()
+    }
+
+    /// The rest of the hint if `field` is somewhere along its way, so
+    /// following it doesn't need another search.
+    pub fn follow(&self, field: &Field) -> Option<Hint> {
+        let mut board = self.board;
+        for (i, &m) in self.moves.iter().enumerate() {
+            if board == *field { return Some(Hint { board, moves: self.moves[i..].to_vec(), optimal: self.optimal }) }
+            board.make_move(m);
+        }
+        (board == *field).then(|| Hint { board, moves: Vec::new(), optimal: self.optimal })
+    }
+}
+
+
+struct Game {
+    field: Field,
+    /// Tile the pointer went down on, and where.
+    press: Option<(u8, Vector2)>,
+    hint: Option<Hint>,
+    /// The hint being looked for in the background, and how to call it off.
+    pending_hint: Option<(Field, Receiver<Option<Hint>>, CancelToken)>,
+    show_distance: bool,
+    session: Session,
+    /// The start board being solved in the background for its optimal length.
+    pending_optimal: Option<(Receiver<Option<Hint>>, CancelToken)>,
+}
+
+impl Game {
+    fn new(field: Field, goal: Field) -> Game {
+        let (sender, receiver) = mpsc::channel();
+        let cancel = CancelToken::new();
+        let thread_cancel = cancel.clone();
+        thread::spawn(move || { let _ = sender.send(Hint::find(&field, &goal, OPTIMAL_TIME, &thread_cancel)); });
+
+        Game {
+            field, press: None, hint: None, pending_hint: None, show_distance: false,
+            session: Session::new(&field, &goal), pending_optimal: Some((receiver, cancel)),
+        }
+    }
+
+    /// Counts `moves` just made, and announces the win.
+    fn moved(&mut self, moves: usize) {
+        if let Some(victory) = self.session.record(moves, &self.field) {
+            println!("{}", victory);
+        }
+    }
+
+    /// Back to the start board for another go.
+    fn restart(&mut self) {
+        self.cancel_hint();
+        self.field = *self.session.start();
+        self.hint = None;
+        let optimal = self.session.optimal();
+        self.session = Session::new(&self.field, self.session.goal());
+        if let Some(optimal) = optimal { self.session.set_optimal(optimal) }
+    }
+
+    fn update_session(&mut self, frame_time: f32) {
+        self.session.tick(Duration::from_secs_f32(frame_time));
+        if let Some((receiver, _)) = &self.pending_optimal {
+            match receiver.try_recv() {
+                Ok(hint) => {
+                    if let Some(hint) = hint.filter(|hint| hint.optimal) { self.session.set_optimal(hint.distance()) }
+                    self.pending_optimal = None;
+                }
+                Err(mpsc::TryRecvError::Disconnected) => self.pending_optimal = None,
+                Err(mpsc::TryRecvError::Empty) => {}
+            }
+        }
+    }
+
+    /// Starts looking for a hint for the board as it is, unless there is one already.
+    fn request_hint(&mut self) {
+        if self.hint.as_ref().is_some_and(|hint| hint.board == self.field) { return }
+        if self.pending_hint.as_ref().is_some_and(|(board, _, _)| *board == self.field) { return }
+        self.cancel_hint();
+
+        let (sender, receiver) = mpsc::channel();
+        let (board, goal, cancel) = (self.field, *self.session.goal(), CancelToken::new());
+        let thread_cancel = cancel.clone();
+        thread::spawn(move || { let _ = sender.send(Hint::find(&board, &goal, HINT_TIME, &thread_cancel)); });
+        self.pending_hint = Some((board, receiver, cancel));
+        self.session.hint_used();
+    }
+
+    fn cancel_hint(&mut self) {
+        if let Some((_, _, cancel)) = self.pending_hint.take() { cancel.cancel() }
+    }
+
+    /// Picks up a hint that's done, and keeps the one there is in step with the board.
+    fn update_hint(&mut self) {
+        if let Some((_, receiver, _)) = &self.pending_hint {
+            match receiver.try_recv() {
+                Ok(hint) => { self.hint = hint; self.pending_hint = None; }
+                Err(mpsc::TryRecvError::Disconnected) => self.pending_hint = None,
+                Err(mpsc::TryRecvError::Empty) => {}
+            }
+        }
+        self.hint = self.hint.take().and_then(|hint| hint.follow(&self.field));
+        if self.pending_hint.as_ref().is_some_and(|(board, _, _)| *board != self.field) { self.cancel_hint() }
+    }
+
+    /// Marks the hinted tile and writes the distance in the blank cell.
+    fn draw_hint(&self, gfx: &mut RaylibDrawHandle<'_>) {
+        let width = self.field.width();
+        let corner = |cell: u8| (CELL_SIZE * (cell % width) as i32, CELL_SIZE * (cell / width) as i32);
+        if let Some(cell) = self.hint.as_ref().and_then(Hint::next_tile) {
+            let (x, y) = corner(cell);
+            gfx.draw_rectangle(x, y, CELL_SIZE, CELL_SIZE, Color::GOLD);
+        }
+
+        let (x, y) = corner(self.field.get_empty_index());
+        let text = match (&self.hint, &self.pending_hint) {
+            (_, Some(_)) => String::from("..."),
+            (Some(hint), None) if self.show_distance => format!("{}{}", if hint.optimal { "" } else { "~" }, hint.distance()),
+            _ => return,
+        };
+        gfx.draw_text(text.as_str(), x + TEXT_OFFSET / 2, y + TEXT_OFFSET, 20, Color::GRAY);
+    }
+
+    /// The move counter and clock under the board, and the victory screen over it once solved.
+    fn draw_session(&self, gfx: &mut RaylibDrawHandle<'_>) {
+        let (width, height) = (CELL_SIZE * self.field.width() as i32, CELL_SIZE * self.field.height() as i32);
+        gfx.draw_rectangle(0, height, width, STATUS_HEIGHT, Color::LIGHTGRAY);
+        let status = format!("{} moves {}", self.session.moves(), session::format_time(self.session.elapsed()));
+        gfx.draw_text(status.as_str(), 6, height + 6, 20, Color::BLACK);
+
+        let Some(victory) = self.session.victory() else { return };
+        gfx.draw_rectangle(0, 0, width, height, Color::WHITE.fade(0.85));
+        let mut lines = vec![String::from("Solved!"), format!("{} moves", victory.moves), session::format_time(victory.elapsed)];
+        if let (Some(optimal), Some(efficiency)) = (victory.optimal, victory.efficiency()) {
+            lines.push(format!("{} optimal", optimal));
+            lines.push(format!("{:.0}%", efficiency * 100.0));
+        }
+        lines.push(String::from("Enter again"));
+        for (i, line) in lines.iter().enumerate() {
+            let color = if i == 0 { Color::DARKGREEN } else { Color::BLACK };
+            gfx.draw_text(line.as_str(), 6, 6 + 22 * i as i32, 20, color);
+        }
+    }
+}
+
+
+/// Opens a window with `field` in it, to be brought to `goal`, until it's closed. The arrow keys move
+/// the blank, and clicking or dragging a tile in the blank's row or column
+/// slides it and the tiles up to the blank. Touch screens send their taps
+/// through as mouse clicks.
+///
+/// H asks for a hint, solved in the background and marked on the tile to
+/// move, and D shows how many moves are left with it, with a ~ when they
+/// might not be the fewest.
+///
+/// The moves and time so far are shown under the board. Solving it brings
+/// up how it went next to the optimal solution, and Enter starts over.
+pub fn play(field: Field, goal: Field) {
+    let mut game = Game::new(field, goal);
+    let (mut handle, thread) = raylib::init()
+        .size(CELL_SIZE * game.field.width() as i32, CELL_SIZE * game.field.height() as i32 + STATUS_HEIGHT)
+        .title("Пятнашки")
+        .build();
+
+    loop {
+        // Input
+        {
+            process_input(&mut game, &mut handle);
+        }
+
+        // Draw
+        {
+            let mut gfx = handle.begin_drawing(&thread);
+
+            gfx.clear_background(Color::WHITE);
+            // gfx.draw_fps(0, 0);
+            game.draw_hint(&mut gfx);
+            game.draw_session(&mut gfx);
+            game.field.draw(gfx);
+            //drop(gfx);
+        }
+
+        if handle.window_should_close() { break }
+    }
+    game.cancel_hint();
+    if let Some((_, cancel)) = game.pending_optimal.take() { cancel.cancel() }
+}
+
+
+#[derive(PartialEq)]
+enum KeyPressResult {
+    None,
+    Success,
+    Failure,
+}
+
+fn key_pressed(game: &mut Game, key: KeyboardKey) {
+    if game.session.is_finished() {
+        if key == KeyboardKey::KEY_ENTER { game.restart() }
+        return;
+    }
+
+    let result = match key {
+        KeyboardKey::KEY_UP     => { if game.field.up() { KeyPressResult::Success } else { KeyPressResult::Failure } }
+        KeyboardKey::KEY_DOWN   => { if game.field.down() { KeyPressResult::Success } else { KeyPressResult::Failure }   }
+        KeyboardKey::KEY_LEFT   => { if game.field.left() { KeyPressResult::Success } else { KeyPressResult::Failure } }
+        KeyboardKey::KEY_RIGHT  => { if game.field.right() { KeyPressResult::Success } else { KeyPressResult::Failure } }
+        KeyboardKey::KEY_H      => { game.request_hint(); KeyPressResult::Success }
+        KeyboardKey::KEY_D      => { game.show_distance = !game.show_distance; if game.show_distance { game.request_hint() } KeyPressResult::Success }
+
+        _ => { KeyPressResult::None }
+    };
+
+    if result == KeyPressResult::Failure {
+        println!("Bad move");
+    }
+    if result == KeyPressResult::Success && matches!(key, KeyboardKey::KEY_UP | KeyboardKey::KEY_DOWN | KeyboardKey::KEY_LEFT | KeyboardKey::KEY_RIGHT) {
+        game.moved(1);
+    }
+}
+
+
+/// Which way the tiles go when the blank makes `m`.
+fn tile_direction(m: Move) -> Vector2 {
+    match m {
+        Move::Up => Vector2::new(0.0, 1.0),
+        Move::Down => Vector2::new(0.0, -1.0),
+        Move::Left => Vector2::new(1.0, 0.0),
+        Move::Right => Vector2::new(-1.0, 0.0),
+    }
+}
+
+fn pointer_released(game: &mut Game, cell: u8, from: Vector2, to: Vector2) {
+    let Some(moves) = game.field.line_moves(cell) else { return };
+    let (dx, dy) = (to.x - from.x, to.y - from.y);
+    let is_click = dx.hypot(dy) < DRAG_THRESHOLD;
+
+    // A drag only counts toward the blank, dragging anywhere else lets go of the tile
+    let direction = tile_direction(moves[0]);
+    if is_click || dx * direction.x + dy * direction.y >= DRAG_THRESHOLD {
+        game.field.shift_line(cell);
+        game.moved(moves.len());
+    }
+}
+
+fn process_pointer(game: &mut Game, handle: &RaylibHandle) {
+    if game.session.is_finished() { game.press = None; return }
+    let position = handle.get_mouse_position();
+    if handle.is_mouse_button_pressed(MouseButton::MOUSE_LEFT_BUTTON) {
+        game.press = None;
+        match cell_at(&game.field, position.x, position.y) {
+            Some(cell) if game.field.line_moves(cell).is_some() => game.press = Some((cell, position)),
+            Some(cell) if cell != game.field.get_empty_index() => println!("Bad move"),
+            _ => {}
+        }
+    }
+    if handle.is_mouse_button_released(MouseButton::MOUSE_LEFT_BUTTON) {
+        if let Some((cell, from)) = game.press.take() {
+            pointer_released(game, cell, from, position);
+        }
+    }
+}
+
+
+fn process_input(game: &mut Game, handle: &mut RaylibHandle) {
+    let mut key_opt = Some(KeyboardKey::KEY_NULL);
+    while key_opt.is_some() {
+        key_opt = handle.get_key_pressed();
+
+        if let Some(key) = key_opt {
+            key_pressed(game, key);
+        }
+    }
+
+    process_pointer(game, handle);
+    game.update_hint();
+    game.update_session(handle.get_frame_time());
+}
+
+
+/// Where a solution's playback is. `position` counts moves made, with the
+/// fraction being how far the next tile has slid, and eases toward `target`.
+pub struct Playback {
+    states: Vec<Field>,
+    moves: Vec<Move>,
+    position: f32,
+    target: f32,
+    playing: bool,
+    /// Moves a second.
+    speed: f32,
+}
+
+impl Playback {
+    pub const MIN_SPEED: f32 = 0.25;
+    pub const MAX_SPEED: f32 = 16.0;
+
+    pub fn new(solution: &AStarSolution) -> Playback {
+        Playback { states: solution.states.clone(), moves: solution.moves.clone(), position: 0.0, target: 0.0, playing: false, speed: 2.0 }
+    }
+
+    /// Moves made so far, counting a tile still sliding as not moved yet,
+    /// unless it's on its way back.
+    pub fn current(&self) -> usize {
+        if self.target < self.position { self.position.ceil() as usize } else { self.position.floor() as usize }
+    }
+
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Plays from where it is, or from the start if it's at the end.
+    pub fn play(&mut self) {
+        if self.position >= self.len() as f32 { self.position = 0.0 }
+        self.playing = true;
+    }
+
+    /// Stops once the tile that's sliding gets where it's going.
+    pub fn pause(&mut self) {
+        self.playing = false;
+        self.target = self.position.ceil();
+    }
+
+    pub fn toggle(&mut self) {
+        if self.playing { self.pause() } else { self.play() }
+    }
+
+    /// Steps from where it's headed, or from where it is while playing,
+    /// since then it's headed for the end.
+    pub fn step_forward(&mut self) {
+        let from = if self.playing { self.position } else { self.target };
+        self.playing = false;
+        self.target = (from.floor() + 1.0).min(self.len() as f32);
+    }
+
+    pub fn step_back(&mut self) {
+        let from = if self.playing { self.position } else { self.target };
+        self.playing = false;
+        self.target = (from.ceil() - 1.0).max(0.0);
+    }
+
+    pub fn restart(&mut self) {
+        self.playing = false;
+        (self.position, self.target) = (0.0, 0.0);
+    }
+
+    pub fn faster(&mut self) {
+        self.speed = (self.speed * 2.0).min(Playback::MAX_SPEED);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed = (self.speed / 2.0).max(Playback::MIN_SPEED);
+    }
+
+    /// Moves things along by `seconds`. Stepping never goes slower than
+    /// two moves a second, so it keeps up with the keys.
+    pub fn update(&mut self, seconds: f32) {
+        if self.playing { self.target = self.len() as f32 }
+        let step = seconds * if self.playing { self.speed } else { self.speed.max(2.0) };
+        self.position = if self.position < self.target {
+            (self.position + step).min(self.target)
+        } else {
+            (self.position - step).max(self.target)
+        };
+        if self.playing && self.position >= self.len() as f32 { self.playing = false }
+    }
+
+    /// The board `position` is on, and the tile that's sliding off it with
+    /// how far along it is.
+    pub fn frame(&self) -> (&Field, Option<(u8, f32)>) {
+        let index = (self.position.floor() as usize).min(self.len());
+        let board = &self.states[index];
+        let progress = self.position - index as f32;
+        if index == self.len() || progress <= 0.0 { return (board, None) }
+        let cell = board.moved_piece_index(self.moves[index]).unwrap();
+        (board, Some((cell, progress)))
+    }
+
+    fn draw(&self, gfx: &mut RaylibDrawHandle<'_>) {
+        let (board, sliding) = self.frame();
+        let width = board.width();
+        let corner = |cell: u8| Vector2::new((CELL_SIZE * (cell % width) as i32) as f32, (CELL_SIZE * (cell / width) as i32) as f32);
+        for i in 0..board.cell_count() {
+            let value = board.get_linear(i);
+            if value == 0 { continue }
+
+            let mut at = corner(i);
+            if let Some((cell, progress)) = sliding.filter(|&(cell, _)| cell == i) {
+                let to = corner(board.get_empty_index());
+                let from = corner(cell);
+                at = Vector2::new(from.x + (to.x - from.x) * progress, from.y + (to.y - from.y) * progress);
+            }
+            draw_tile(gfx, value, at.x.round() as i32, at.y.round() as i32);
+        }
+
+        let top = CELL_SIZE * board.height() as i32;
+        gfx.draw_rectangle(0, top, CELL_SIZE * width as i32, STATUS_HEIGHT, Color::LIGHTGRAY);
+        let state = if self.playing { "" } else { " ||" };
+        let status = format!("{}/{} x{}{}", self.current(), self.len(), self.speed, state);
+        gfx.draw_text(status.as_str(), 6, top + 6, 20, Color::BLACK);
+    }
+}
+
+
+fn playback_key_pressed(playback: &mut Playback, key: KeyboardKey) {
+    match key {
+        KeyboardKey::KEY_SPACE => playback.toggle(),
+        KeyboardKey::KEY_RIGHT => playback.step_forward(),
+        KeyboardKey::KEY_LEFT => playback.step_back(),
+        KeyboardKey::KEY_UP => playback.faster(),
+        KeyboardKey::KEY_DOWN => playback.slower(),
+        KeyboardKey::KEY_HOME => playback.restart(),
+        _ => {}
+    }
+}
+
+/// Opens a window that plays `solution` back, tile by tile. Space plays and
+/// pauses, the left and right arrows step, up and down change the speed and
+/// Home goes back to the start.
+pub fn playback(solution: &AStarSolution) {
+    let mut playback = Playback::new(solution);
+    let start = solution.states[0];
+    let (mut handle, thread) = raylib::init()
+        .size(CELL_SIZE * start.width() as i32, CELL_SIZE * start.height() as i32 + STATUS_HEIGHT)
+        .title("Пятнашки")
+        .build();
+
+    loop {
+        // Input
+        {
+            while let Some(key) = handle.get_key_pressed() {
+                playback_key_pressed(&mut playback, key);
+            }
+            playback.update(handle.get_frame_time());
+        }
+
+        // Draw
+        {
+            let mut gfx = handle.begin_drawing(&thread);
+
+            gfx.clear_background(Color::WHITE);
+            playback.draw(&mut gfx);
+        }
+
+        if handle.window_should_close() { break }
+    }
+}
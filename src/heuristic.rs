@@ -0,0 +1,300 @@
+use std::{rc::Rc, sync::Arc};
+use crate::astar::{calculate_heuristic, calculate_heuristic_change, calculate_linear_conflict, calculate_linear_conflict_change, conflict_penalty, conflict_penalty_change};
+use crate::field::{Field, Move, MAX_CELLS};
+use crate::pdb::PatternDatabase;
+
+
+/// Estimate of the number of moves left to solve a board, used to guide the solvers.
+pub trait Heuristic {
+    fn evaluate(&self, field: &Field) -> f32;
+
+    /// How the estimate changes when `m` is made on `field`. Heuristics that
+    /// can't tell cheaply return `None` and the solvers fall back to `evaluate`.
+    fn change(&self, _field: &Field, _m: Move) -> Option<f32> {
+        None
+    }
+
+    /// Whether the estimate never exceeds the real distance. Only admissible
+    /// heuristics let the solvers guarantee optimal solutions.
+    fn is_admissible(&self) -> bool;
+
+    /// Board the estimate measures the distance to, and so the board solvers
+    /// search toward. The solved board of the given size unless overridden.
+    fn goal(&self, width: u8, height: u8) -> Field {
+        Field::with_size(width, height)
+    }
+
+
+    /// Estimate after making `m` on `field`, whose own estimate is `h`.
+    fn next(&self, field: &Field, h: f32, m: Move) -> f32 {
+        match self.change(field, m) {
+            Some(change) => h + change,
+            None => {
+                let mut moved = *field;
+                moved.make_move(m);
+                self.evaluate(&moved)
+            }
+        }
+    }
+}
+
+
+#[derive(Clone, Copy, Default)]
+pub struct Manhattan;
+
+impl Heuristic for Manhattan {
+    fn evaluate(&self, field: &Field) -> f32 {
+        calculate_heuristic(field)
+    }
+
+    fn change(&self, field: &Field, m: Move) -> Option<f32> {
+        Some(calculate_heuristic_change(field, m))
+    }
+
+    fn is_admissible(&self) -> bool {
+        true
+    }
+}
+
+
+#[derive(Clone, Copy, Default)]
+pub struct LinearConflict;
+
+impl Heuristic for LinearConflict {
+    fn evaluate(&self, field: &Field) -> f32 {
+        calculate_linear_conflict(field)
+    }
+
+    fn change(&self, field: &Field, m: Move) -> Option<f32> {
+        Some(calculate_linear_conflict_change(field, m))
+    }
+
+    fn is_admissible(&self) -> bool {
+        true
+    }
+}
+
+
+/// Manhattan distance to any goal board, not just the solved one.
+#[derive(Clone, Copy)]
+pub struct GoalManhattan {
+    goal: Field,
+    home: [u8; MAX_CELLS],
+}
+
+impl GoalManhattan {
+    pub fn new(goal: &Field) -> GoalManhattan {
+        let mut home = [0; MAX_CELLS];
+        for i in 0..goal.cell_count() {
+            home[goal.get_linear(i) as usize] = i;
+        }
+        GoalManhattan { goal: *goal, home }
+    }
+
+    /// Index `piece` sits at in the goal.
+    pub fn home(&self, piece: u8) -> u8 {
+        self.home[piece as usize]
+    }
+
+    fn distance(&self, piece: u8, index: u8) -> f32 {
+        let (home, width) = (self.home(piece) as i8, self.goal.width() as i8);
+        let index = index as i8;
+        ((home % width - index % width).abs() + (home / width - index / width).abs()) as f32
+    }
+}
+
+impl Heuristic for GoalManhattan {
+    fn evaluate(&self, field: &Field) -> f32 {
+        (0..field.cell_count()).map(|i| {
+            let piece = field.get_linear(i);
+            if piece == 0 { 0.0 } else { self.distance(piece, i) }
+        }).sum()
+    }
+
+    fn change(&self, field: &Field, m: Move) -> Option<f32> {
+        let piece_index = field.moved_piece_index(m)?;
+        let piece = field.get_linear(piece_index);
+        Some(self.distance(piece, field.get_empty_index()) - self.distance(piece, piece_index))
+    }
+
+    fn is_admissible(&self) -> bool {
+        true
+    }
+
+    fn goal(&self, _width: u8, _height: u8) -> Field {
+        self.goal
+    }
+}
+
+
+/// Linear conflict toward any goal board.
+#[derive(Clone, Copy)]
+pub struct GoalLinearConflict(GoalManhattan);
+
+impl GoalLinearConflict {
+    pub fn new(goal: &Field) -> GoalLinearConflict {
+        GoalLinearConflict(GoalManhattan::new(goal))
+    }
+}
+
+impl Heuristic for GoalLinearConflict {
+    fn evaluate(&self, field: &Field) -> f32 {
+        self.0.evaluate(field) + conflict_penalty(field, &|piece| self.0.home(piece))
+    }
+
+    fn change(&self, field: &Field, m: Move) -> Option<f32> {
+        let manhattan = self.0.change(field, m)?;
+        Some(manhattan + conflict_penalty_change(field, m, &|piece| self.0.home(piece)))
+    }
+
+    fn is_admissible(&self) -> bool {
+        true
+    }
+
+    fn goal(&self, width: u8, height: u8) -> Field {
+        self.0.goal(width, height)
+    }
+}
+
+
+impl Heuristic for PatternDatabase {
+    fn evaluate(&self, field: &Field) -> f32 {
+        PatternDatabase::evaluate(self, field)
+    }
+
+    fn change(&self, field: &Field, m: Move) -> Option<f32> {
+        (!self.is_reflected()).then(|| PatternDatabase::change(self, field, m))
+    }
+
+    fn is_admissible(&self) -> bool {
+        true
+    }
+
+    fn goal(&self, _width: u8, _height: u8) -> Field {
+        *PatternDatabase::goal(self)
+    }
+}
+
+
+/// Scales another heuristic by `weight`. Trades optimality for speed when the weight is above 1.
+#[derive(Clone, Copy)]
+pub struct Weighted<H: Heuristic> {
+    pub heuristic: H,
+    pub weight: f32,
+}
+
+impl<H: Heuristic> Weighted<H> {
+    pub fn new(heuristic: H, weight: f32) -> Weighted<H> {
+        Weighted { heuristic, weight }
+    }
+}
+
+impl<H: Heuristic> Heuristic for Weighted<H> {
+    fn evaluate(&self, field: &Field) -> f32 {
+        self.weight * self.heuristic.evaluate(field)
+    }
+
+    fn change(&self, field: &Field, m: Move) -> Option<f32> {
+        self.heuristic.change(field, m).map(|change| self.weight * change)
+    }
+
+    fn is_admissible(&self) -> bool {
+        self.weight <= 1.0 && self.heuristic.is_admissible()
+    }
+
+    fn goal(&self, width: u8, height: u8) -> Field {
+        self.heuristic.goal(width, height)
+    }
+}
+
+
+/// Runtime choice between the built-in heuristics.
+#[derive(Clone, Default)]
+pub enum HeuristicKind {
+    #[default]
+    Manhattan,
+    LinearConflict,
+    PatternDatabase(Arc<PatternDatabase>),
+    GoalManhattan(GoalManhattan),
+    GoalLinearConflict(GoalLinearConflict),
+}
+
+impl Heuristic for HeuristicKind {
+    fn evaluate(&self, field: &Field) -> f32 {
+        match self {
+            HeuristicKind::Manhattan => Manhattan.evaluate(field),
+            HeuristicKind::LinearConflict => LinearConflict.evaluate(field),
+            HeuristicKind::PatternDatabase(pdb) => pdb.evaluate(field),
+            HeuristicKind::GoalManhattan(heuristic) => heuristic.evaluate(field),
+            HeuristicKind::GoalLinearConflict(heuristic) => heuristic.evaluate(field),
+        }
+    }
+
+    fn change(&self, field: &Field, m: Move) -> Option<f32> {
+        match self {
+            HeuristicKind::Manhattan => Manhattan.change(field, m),
+            HeuristicKind::LinearConflict => LinearConflict.change(field, m),
+            HeuristicKind::PatternDatabase(pdb) => Heuristic::change(pdb.as_ref(), field, m),
+            HeuristicKind::GoalManhattan(heuristic) => heuristic.change(field, m),
+            HeuristicKind::GoalLinearConflict(heuristic) => heuristic.change(field, m),
+        }
+    }
+
+    fn is_admissible(&self) -> bool {
+        true
+    }
+
+    fn goal(&self, width: u8, height: u8) -> Field {
+        match self {
+            HeuristicKind::Manhattan | HeuristicKind::LinearConflict => Field::with_size(width, height),
+            HeuristicKind::PatternDatabase(pdb) => *PatternDatabase::goal(pdb),
+            HeuristicKind::GoalManhattan(heuristic) => heuristic.goal(width, height),
+            HeuristicKind::GoalLinearConflict(heuristic) => heuristic.goal(width, height),
+        }
+    }
+}
+
+
+macro_rules! impl_heuristic_for_pointer {
+    ($($pointer:ident),*) => {
+        $(
+            impl<H: Heuristic + ?Sized> Heuristic for $pointer<H> {
+                fn evaluate(&self, field: &Field) -> f32 {
+                    (**self).evaluate(field)
+                }
+
+                fn change(&self, field: &Field, m: Move) -> Option<f32> {
+                    (**self).change(field, m)
+                }
+
+                fn is_admissible(&self) -> bool {
+                    (**self).is_admissible()
+                }
+
+                fn goal(&self, width: u8, height: u8) -> Field {
+                    (**self).goal(width, height)
+                }
+            }
+        )*
+    };
+}
+
+impl_heuristic_for_pointer!(Box, Rc, Arc);
+
+impl<H: Heuristic + ?Sized> Heuristic for &H {
+    fn evaluate(&self, field: &Field) -> f32 {
+        (**self).evaluate(field)
+    }
+
+    fn change(&self, field: &Field, m: Move) -> Option<f32> {
+        (**self).change(field, m)
+    }
+
+    fn is_admissible(&self) -> bool {
+        (**self).is_admissible()
+    }
+
+    fn goal(&self, width: u8, height: u8) -> Field {
+        (**self).goal(width, height)
+    }
+}
@@ -0,0 +1,44 @@
+//! Fifteen puzzle boards of any size from 2x2 to 5x5 and the solvers for them.
+//!
+//! `Field` is the board and `Move` moves its blank. Every solver implements
+//! `Solver`, and takes its heuristic as a type parameter:
+//!
+//! ```no_run
+//! use pyatnahi::{Field, IDAStar, LinearConflict, Solver, SolveOptions};
+//!
+//! let start = Field::from_string("51247308A6BE9FCD").unwrap();
+//! let solution = IDAStar::with_heuristic(&start, LinearConflict).solve(&start, &SolveOptions::default()).unwrap();
+//! println!("{} moves: {}", solution.moves.len(), pyatnahi::MoveSequence::from(solution.moves));
+//! ```
+//!
+//! The raylib window lives in `gui`, behind the `gui` feature. It's off by
+//! default, so the solvers build anywhere without cmake or a C compiler.
+
+// Parsing boards fails with () and always has, there's only one way it can go wrong
+#![allow(clippy::result_unit_err)]
+
+pub mod astar;
+pub mod bidirectional;
+pub mod enumerate;
+pub mod field;
+#[cfg(feature = "gui")]
+pub mod gui;
+pub mod heuristic;
+pub mod moves;
+pub mod parallel;
+pub mod pdb;
+pub mod rank;
+pub mod scramble;
+pub mod session;
+pub mod solver;
+mod tests;
+
+pub use astar::{AStar, AStarSolution, AnytimeAStar, IDAStar};
+pub use bidirectional::BidirectionalAStar;
+pub use field::{Field, Move};
+pub use heuristic::{Heuristic, HeuristicKind, LinearConflict, Manhattan};
+pub use moves::MoveSequence;
+pub use parallel::ParallelIDAStar;
+pub use pdb::PatternDatabase;
+pub use session::{Session, Victory};
+pub use solver::{SearchStats, SolveError, SolveOptions, Solver};
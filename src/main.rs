@@ -1,77 +1,111 @@
+use std::env;
 use std::io::{self, Write};
+use std::process::ExitCode;
 use std::str::FromStr;
 // use std::{thread, time::Duration};
-use raylib::prelude::*;
-use raylib::prelude::RaylibDraw;
+use pyatnahi::field;
 
-
-mod field;
-use crate::astar::AStarSolution;
-use crate::field::Drawable;
-
-mod astar;
-mod tests;
+mod cli;
+mod cli_tests;
 
 
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let cli = match cli::parse(&args) {
+        Ok(cli) => cli,
+        Err(error) => {
+            eprintln!("error: {}\nRun with --help to see the commands and options.", error);
+            return ExitCode::from(2);
+        }
+    };
 
-struct Game {
-    field: field::Field
+    match &cli.command {
+        cli::Command::Interactive => {
+            if let Err(error) = interactive(&cli.options) { eprintln!("error: {}", error); return ExitCode::FAILURE; }
+            ExitCode::SUCCESS
+        }
+        cli::Command::Help => { println!("{}", cli::USAGE); ExitCode::SUCCESS }
+        cli::Command::Play(board) => {
+            let game = cli.options.board(board).and_then(|field| Ok((field, cli.options.goal(field.width(), field.height())?)));
+            match game {
+                Ok((field, goal)) if field.is_solvable_toward(&goal) => play(field, goal),
+                Ok(_) => { eprintln!("error: field isn't solvable"); ExitCode::FAILURE }
+                Err(error) => { eprintln!("error: {}", error); ExitCode::FAILURE }
+            }
+        }
+        cli::Command::Watch(board) => {
+            let solution = cli.options.board(board).and_then(|field| cli::solve_board(&field, &cli.options).map(|solution| (field, solution)));
+            match solution {
+                Ok((field, solution)) => {
+                    cli::print_solution(&field, &solution, cli.options.format, cli.options.notation);
+                    watch(&solution)
+                }
+                Err(error) => { eprintln!("error: {}", error); ExitCode::FAILURE }
+            }
+        }
+        _ => cli::run(&cli),
+    }
 }
 
 
-fn main() -> io::Result<()> {
-    let mut game : Game = Game {
-        field: field::Field::new()
-    };
-
-    loop {
+/// The original prompts, for when the program is started without arguments.
+fn interactive(options: &cli::Options) -> io::Result<()> {
+    let (game, goal) = loop {
         let input: String = read("Field: 0x");
 
         let result = field::Field::from_string(input).ok();
         if result.is_none() { println!("Incorrent input!"); continue; }
 
         let field = result.unwrap();
-        if !field.is_solvable() { println!("Field isn't solvable!"); continue; }
+        let goal = match options.goal(field.width(), field.height()) {
+            Ok(goal) => goal,
+            Err(error) => { println!("{}", error); continue; }
+        };
+        if !field.is_solvable_toward(&goal) { println!("Field isn't solvable!"); continue; }
 
-        game.field = field;
-        break;
-    }
+        break (field, goal);
+    };
 
-    game.field.display();
+    game.display();
     println!("\n<=======>\n");
     
     if read("Do you want to: \n- Initialize solver? (true)\n- Play it yourself? (false)\n> (true/false): ") {
-        init_ida_solver(&mut game);
+        let options = cli::Options { verbose: true, ..options.clone() };
+        match cli::solve_board(&game, &options) {
+            Ok(solution) => cli::print_solution(&game, &solution, cli::Format::Text, options.notation),
+            Err(error) => println!("Couldn't solve: {}", error),
+        }
         return Ok(());
     }
     
     println!("\n");
+    play(game, goal);
+    Ok(())
+}
 
-    let (mut handle, thread) = raylib::init()
-        .size(256, 256)
-        .title("Пятнашки")
-        .build();
-    
-    loop {
-        // Input
-        {
-            process_input(&mut game, &mut handle);
-        }
 
-        // Draw
-        {
-            let mut gfx = handle.begin_drawing(&thread);
-            
-            gfx.clear_background(Color::WHITE);
-            // gfx.draw_fps(0, 0);
-            game.field.draw(gfx);
-            //drop(gfx);
-        }
-        
-        if handle.window_should_close() { break }
-    }
+#[cfg(feature = "gui")]
+fn play(field: field::Field, goal: field::Field) -> ExitCode {
+    pyatnahi::gui::play(field, goal);
+    ExitCode::SUCCESS
+}
 
-    Ok(())
+#[cfg(not(feature = "gui"))]
+fn play(_: field::Field, _: field::Field) -> ExitCode {
+    eprintln!("error: playing needs a window, build with --features gui for one");
+    ExitCode::FAILURE
+}
+
+#[cfg(feature = "gui")]
+fn watch(solution: &pyatnahi::AStarSolution) -> ExitCode {
+    pyatnahi::gui::playback(solution);
+    ExitCode::SUCCESS
+}
+
+#[cfg(not(feature = "gui"))]
+fn watch(_: &pyatnahi::AStarSolution) -> ExitCode {
+    eprintln!("error: watching needs a window, build with --features gui for one");
+    ExitCode::FAILURE
 }
 
 
@@ -89,80 +123,3 @@ fn read<T: FromStr>(name: &str) -> T {
         }
     }
 }
-
-
-fn init_solver(game: &mut Game) {
-    let mut star = astar::AStar::new(&game.field);
-    let mut result_option: Option<Result<AStarSolution, ()>> = Default::default();
-
-    const MAX_STEPS: i32 = 160000000;
-    let mut steps = 0;
-    while result_option.is_none() && steps < MAX_STEPS {
-        result_option = star.step();
-        steps += 1;
-    }
-
-    let Some(result) = result_option
-    else { println!("Step limit exceeded!"); return; };
-
-    let Ok(solution) = result
-    else { println!("Something went wrong..."); return; };
-
-    println!("===========\n{} Moves:", solution.moves.len());
-    solution.moves.iter().for_each(|x| {
-        print!("{}", x);
-    });
-
-    println!("\n===========\n");
-}
-
-fn init_ida_solver(game: &mut Game) {
-    let mut idastar = astar::IDAStar::new(&game.field);
-    let result = idastar.run();
-
-    let Ok(solution) = result
-    else { println!("Something went wrong..."); return; };
-
-    println!("===========\n{} Moves:", solution.moves.len());
-    solution.moves.iter().for_each(|x| {
-        print!("{}", x);
-    });
-
-    println!("\n===========\n");
-}
-
-
-
-#[derive(PartialEq)]
-enum KeyPressResult {
-    None,
-    Success,
-    Failure,
-}
-
-fn key_pressed(game: &mut Game, key: KeyboardKey) {
-    let result = match key {
-        KeyboardKey::KEY_UP     => { if game.field.up() { KeyPressResult::Success } else { KeyPressResult::Failure } }
-        KeyboardKey::KEY_DOWN   => { if game.field.down() { KeyPressResult::Success } else { KeyPressResult::Failure }   }
-        KeyboardKey::KEY_LEFT   => { if game.field.left() { KeyPressResult::Success } else { KeyPressResult::Failure } }
-        KeyboardKey::KEY_RIGHT  => { if game.field.right() { KeyPressResult::Success } else { KeyPressResult::Failure } }
-
-        _ => { KeyPressResult::None }
-    };
-
-    if result == KeyPressResult::Failure {
-        println!("Bad move");
-    }
-}
-
-
-fn process_input(game: &mut Game, handle: &mut RaylibHandle) {
-    let mut key_opt = Some(KeyboardKey::KEY_NULL);
-    while key_opt.is_some() {
-        key_opt = handle.get_key_pressed();
-
-        if let Some(key) = key_opt {
-            key_pressed(game, key);
-        }
-    }
-}
@@ -0,0 +1,194 @@
+use std::{fmt, str::FromStr};
+use crate::field::{Field, Move};
+use crate::solver::{self, VerifyError};
+
+
+/// Ways to write moves down. All but `Tiles` move the blank.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Notation {
+    /// `^v<>`, like `Move` displays.
+    Arrows,
+    /// `UDLR`.
+    Letters,
+    /// Arrows with runs written once with a count, `^^^<` as `^3<`.
+    Compressed,
+    /// The number of every tile slid into the blank, `12 8 4`. Needs the start board.
+    Tiles,
+}
+
+impl FromStr for Notation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "arrows" => Ok(Notation::Arrows),
+            "udlr" => Ok(Notation::Letters),
+            "compressed" => Ok(Notation::Compressed),
+            "tiles" => Ok(Notation::Tiles),
+            _ => Err(format!("unknown notation '{}'", s)),
+        }
+    }
+}
+
+
+fn letter(m: Move) -> char {
+    match m {
+        Move::Up => 'U',
+        Move::Down => 'D',
+        Move::Left => 'L',
+        Move::Right => 'R',
+    }
+}
+
+
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct MoveSequence(Vec<Move>);
+
+impl MoveSequence {
+    pub fn new(moves: Vec<Move>) -> MoveSequence {
+        MoveSequence(moves)
+    }
+
+    pub fn moves(&self) -> &[Move] {
+        &self.0
+    }
+
+    pub fn into_moves(self) -> Vec<Move> {
+        self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+
+    /// Reads moves in any notation. Tile numbers are told apart by being
+    /// nothing but numbers, and need `start` to be turned into moves.
+    pub fn parse(s: &str, start: &Field) -> Result<MoveSequence, String> {
+        let is_tiles = s.chars().any(|c| c.is_ascii_digit())
+            && s.chars().all(|c| c.is_ascii_digit() || c.is_whitespace() || c == ',');
+        if is_tiles { MoveSequence::parse_tiles(s, start) } else { s.parse() }
+    }
+
+    /// Reads tile numbers separated by spaces or commas, each tile next to
+    /// the blank at the time.
+    pub fn parse_tiles(s: &str, start: &Field) -> Result<MoveSequence, String> {
+        let mut board = *start;
+        let mut moves = Vec::new();
+        for (index, tile) in s.split([' ', ',', '\t', '\n']).filter(|tile| !tile.is_empty()).enumerate() {
+            let tile: u8 = tile.parse().map_err(|_| format!("invalid tile '{}'", tile))?;
+            let m = Move::iter()
+                .find(|&m| board.moved_piece_index(m).is_some_and(|i| board.get_linear(i) == tile))
+                .ok_or_else(|| format!("tile {} (move {}) isn't next to the blank on {}", tile, index + 1, board.get_string()))?;
+            board.make_move(m);
+            moves.push(m);
+        }
+        Ok(MoveSequence(moves))
+    }
+
+    /// Writes the moves down in `notation`. Only `Tiles` looks at `start`,
+    /// and fails if a move can't be made on the way.
+    pub fn format(&self, notation: Notation, start: &Field) -> Result<String, VerifyError> {
+        Ok(match notation {
+            Notation::Arrows => self.to_string(),
+            Notation::Letters => self.0.iter().map(|&m| letter(m)).collect(),
+            Notation::Compressed => {
+                let mut s = String::new();
+                for run in self.0.chunk_by(|a, b| a == b) {
+                    s += &run[0].to_string();
+                    if run.len() > 1 { s += &run.len().to_string() }
+                }
+                s
+            }
+            Notation::Tiles => {
+                let states = self.replay(start)?;
+                let tiles: Vec<String> = self.0.iter().zip(&states)
+                    .map(|(&m, board)| board.get_linear(board.moved_piece_index(m).unwrap()).to_string())
+                    .collect();
+                tiles.join(" ")
+            }
+        })
+    }
+
+
+    /// The moves that take the end board back to the start.
+    pub fn invert(&self) -> MoveSequence {
+        MoveSequence(self.0.iter().rev().map(Move::inverse).collect())
+    }
+
+    /// Drops every move that's undone right after, over and over, so `^<>v`
+    /// comes out empty. Ends on the same board in fewer moves.
+    pub fn cancel_inverses(&self) -> MoveSequence {
+        let mut moves: Vec<Move> = Vec::with_capacity(self.0.len());
+        for &m in &self.0 {
+            if moves.last() == Some(&m.inverse()) { moves.pop(); } else { moves.push(m) }
+        }
+        MoveSequence(moves)
+    }
+
+
+    /// Every board along the way, `start` first.
+    pub fn replay(&self, start: &Field) -> Result<Vec<Field>, VerifyError> {
+        solver::replay(start, &self.0)
+    }
+
+    /// The board the moves end on.
+    pub fn apply(&self, start: &Field) -> Result<Field, VerifyError> {
+        Ok(*self.replay(start)?.last().unwrap())
+    }
+}
+
+
+impl From<Vec<Move>> for MoveSequence {
+    fn from(moves: Vec<Move>) -> Self {
+        MoveSequence(moves)
+    }
+}
+
+impl From<&[Move]> for MoveSequence {
+    fn from(moves: &[Move]) -> Self {
+        MoveSequence(moves.to_vec())
+    }
+}
+
+
+impl fmt::Display for MoveSequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for m in &self.0 {
+            write!(f, "{}", m)?;
+        }
+        Ok(())
+    }
+}
+
+/// Arrows or UDLR, either case, mixed as you like, each optionally followed
+/// by a repeat count. Spaces and commas are skipped.
+impl FromStr for MoveSequence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut moves = Vec::new();
+        let mut chars = s.chars().filter(|c| !c.is_whitespace() && *c != ',').peekable();
+        while let Some(c) = chars.next() {
+            let m = match c {
+                '^' | 'U' | 'u' => Move::Up,
+                'v' | 'D' | 'd' => Move::Down,
+                '<' | 'L' | 'l' => Move::Left,
+                '>' | 'R' | 'r' => Move::Right,
+                _ => return Err(format!("invalid move '{}'", c)),
+            };
+
+            let mut count = String::new();
+            while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                count.push(digit);
+            }
+            let count = if count.is_empty() { 1 } else { count.parse().map_err(|_| format!("invalid count '{}'", count))? };
+            moves.extend(std::iter::repeat_n(m, count));
+        }
+        Ok(MoveSequence(moves))
+    }
+}
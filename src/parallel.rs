@@ -0,0 +1,321 @@
+use std::{sync::{Mutex, atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering}}, thread, time::Duration};
+use crate::astar::AStarSolution;
+use crate::field::{Field, Move};
+use crate::heuristic::{Heuristic, Manhattan};
+use crate::solver::{Budget, ProgressReporter, SearchStats, SolveError, SolveOptions, Solver};
+
+
+const FRONTIER_NODES_PER_THREAD: usize = 64;
+const MAX_FRONTIER_DEPTH: usize = 16;
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+
+struct FrontierNode {
+    field: Field,
+    h: f32,
+    path: Vec<Move>,
+}
+
+
+/// State shared by the workers of one IDA* iteration.
+struct Shared<'a> {
+    frontier: &'a [FrontierNode],
+    goal: Field,
+    bound: f32,
+    next: AtomicUsize,
+    /// Lowest frontier index a solution was found under so far.
+    found: AtomicUsize,
+    /// Lowest frontier index the budget stopped before it was searched
+    /// through. A solution under a later index might not be the first.
+    unfinished: AtomicUsize,
+    stop: AtomicBool,
+    working: AtomicUsize,
+    next_bound: Mutex<f32>,
+    solutions: Mutex<Vec<(usize, Vec<Move>)>>,
+    expanded: AtomicU64,
+    generated: AtomicU64,
+    depth: AtomicUsize,
+}
+
+impl Shared<'_> {
+    fn stats(&self) -> SearchStats {
+        let depth = self.depth.load(Ordering::Relaxed);
+        SearchStats {
+            expanded: self.expanded.load(Ordering::Relaxed),
+            generated: self.generated.load(Ordering::Relaxed),
+            peak_open: depth,
+            bound: self.bound,
+            depth,
+            elapsed: Duration::ZERO,
+        }
+    }
+}
+
+
+enum SearchResult {
+    Found,
+    Exceeded(f32),
+    Stopped,
+}
+
+
+struct Worker<'a, H: Heuristic> {
+    heuristic: &'a H,
+    shared: &'a Shared<'a>,
+    index: usize,
+    field: Field,
+    path: Vec<Move>,
+    expanded: u64,
+    generated: u64,
+    depth: usize,
+}
+
+impl<H: Heuristic> Worker<'_, H> {
+    fn run(&mut self) {
+        let shared = self.shared;
+        loop {
+            self.index = shared.next.fetch_add(1, Ordering::Relaxed);
+            if self.index >= shared.frontier.len() || self.index > shared.found.load(Ordering::Relaxed) { break }
+            if shared.stop.load(Ordering::Relaxed) {
+                shared.unfinished.fetch_min(self.index, Ordering::Relaxed);
+                break;
+            }
+
+            let node = &shared.frontier[self.index];
+            self.field = node.field;
+            self.path.clone_from(&node.path);
+            match self.search(node.path.len() as f32, node.h) {
+                SearchResult::Found => {
+                    shared.solutions.lock().unwrap().push((self.index, self.path.clone()));
+                    shared.found.fetch_min(self.index, Ordering::Relaxed);
+                }
+                SearchResult::Exceeded(t) => {
+                    let mut next_bound = shared.next_bound.lock().unwrap();
+                    if t < *next_bound { *next_bound = t }
+                }
+                SearchResult::Stopped => {
+                    if shared.stop.load(Ordering::Relaxed) { shared.unfinished.fetch_min(self.index, Ordering::Relaxed); }
+                }
+            }
+        }
+
+        self.flush();
+        shared.working.fetch_sub(1, Ordering::Release);
+    }
+
+    fn flush(&mut self) {
+        self.shared.expanded.fetch_add(self.expanded, Ordering::Relaxed);
+        self.shared.generated.fetch_add(self.generated, Ordering::Relaxed);
+        self.shared.depth.fetch_max(self.depth, Ordering::Relaxed);
+        self.expanded = 0;
+        self.generated = 0;
+    }
+
+    fn search(&mut self, g: f32, h: f32) -> SearchResult {
+        let f = g + h;
+        if f > self.shared.bound { return SearchResult::Exceeded(f) }
+        if self.field == self.shared.goal { return SearchResult::Found }
+
+        // Somebody else already has a solution that comes first, or we're out of budget
+        if self.shared.stop.load(Ordering::Relaxed) || self.shared.found.load(Ordering::Relaxed) < self.index {
+            return SearchResult::Stopped
+        }
+
+        self.expanded += 1;
+        self.depth = self.depth.max(self.path.len());
+        if self.expanded.is_multiple_of(1024) { self.flush() }
+
+        let mut min = f32::INFINITY;
+        for m in Move::iter() {
+            if self.path.last() == Some(&m.inverse()) { continue }
+            if !self.field.is_valid_move(m) { continue }
+
+            let child_h = self.heuristic.next(&self.field, h, m);
+            self.field.make_move(m);
+            self.path.push(m);
+            self.generated += 1;
+
+            match self.search(g + 1.0, child_h) {
+                SearchResult::Exceeded(t) => {
+                    if t < min { min = t }
+                }
+                result => return result,
+            }
+
+            self.path.pop();
+            self.field.make_move(m.inverse());
+        }
+
+        SearchResult::Exceeded(min)
+    }
+}
+
+
+/// IDA* that splits every iteration over a thread pool. All paths up to a
+/// shallow frontier depth are listed in move order and handed out to the
+/// workers one by one. The solution under the lowest frontier node wins, so
+/// the result is the same as the single-threaded `IDAStar` on every run.
+/// Running out of budget before every node ahead of a solution is searched
+/// through is an error, even with that solution in hand.
+pub struct ParallelIDAStar<H: Heuristic + Sync = Manhattan> {
+    heuristic: H,
+    threads: usize,
+    frontier_depth: Option<usize>,
+}
+
+
+impl ParallelIDAStar {
+    pub fn new() -> ParallelIDAStar {
+        ParallelIDAStar::with_heuristic(Manhattan)
+    }
+}
+
+impl Default for ParallelIDAStar {
+    fn default() -> Self {
+        ParallelIDAStar::new()
+    }
+}
+
+
+impl<H: Heuristic + Sync> ParallelIDAStar<H> {
+    pub fn with_heuristic(heuristic: H) -> ParallelIDAStar<H> {
+        ParallelIDAStar {
+            heuristic,
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+            frontier_depth: None,
+        }
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> ParallelIDAStar<H> {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Depth to split the work at. By default the frontier grows until every thread has plenty of nodes.
+    pub fn with_frontier_depth(mut self, depth: usize) -> ParallelIDAStar<H> {
+        self.frontier_depth = Some(depth);
+        self
+    }
+
+
+    /// Every path from `start` down to the frontier depth, in move order.
+    /// Returns the path to the goal instead if one is shorter than that.
+    fn frontier(&self, start: &Field, goal: &Field) -> Result<Vec<FrontierNode>, Vec<Move>> {
+        let target = FRONTIER_NODES_PER_THREAD * self.threads;
+        let mut level = vec![FrontierNode { field: *start, h: self.heuristic.evaluate(start), path: Vec::new() }];
+        let mut depth = 0;
+        loop {
+            if let Some(node) = level.iter().find(|node| node.field == *goal) { return Err(node.path.clone()) }
+
+            let deep_enough = match self.frontier_depth {
+                Some(frontier_depth) => depth >= frontier_depth,
+                None => level.len() >= target || depth >= MAX_FRONTIER_DEPTH,
+            };
+            if deep_enough { return Ok(level) }
+
+            let mut next = Vec::with_capacity(level.len() * 3);
+            for node in &level {
+                for m in Move::iter() {
+                    if node.path.last() == Some(&m.inverse()) { continue }
+                    if !node.field.is_valid_move(m) { continue }
+
+                    let mut child = FrontierNode { field: node.field, h: self.heuristic.next(&node.field, node.h, m), path: node.path.clone() };
+                    child.field.make_move(m);
+                    child.path.push(m);
+                    next.push(child);
+                }
+            }
+            level = next;
+            depth += 1;
+        }
+    }
+}
+
+
+impl<H: Heuristic + Sync> Solver for ParallelIDAStar<H> {
+    fn solve(&mut self, start: &Field, options: &SolveOptions) -> Result<AStarSolution, SolveError> {
+        let goal = self.heuristic.goal(start.width(), start.height());
+        if !start.is_solvable_toward(&goal) { return Err(SolveError::Unsolvable) }
+        let budget = Budget::new(options);
+        let mut reporter = ProgressReporter::new(options);
+
+        let frontier = match self.frontier(start, &goal) {
+            Ok(frontier) => frontier,
+            Err(moves) => {
+                let mut stats = SearchStats { bound: moves.len() as f32, depth: moves.len(), ..Default::default() };
+                reporter.finish(&mut stats);
+                return Ok(AStarSolution::from_moves(start, moves, stats));
+            }
+        };
+        let memory = frontier.len() * std::mem::size_of::<FrontierNode>() + frontier.iter().map(|node| node.path.capacity()).sum::<usize>();
+
+        let mut bound = self.heuristic.evaluate(start);
+        let mut totals = SearchStats::default();
+        loop {
+            let shared = Shared {
+                frontier: &frontier,
+                goal,
+                bound,
+                next: AtomicUsize::new(0),
+                found: AtomicUsize::new(usize::MAX),
+                unfinished: AtomicUsize::new(usize::MAX),
+                stop: AtomicBool::new(false),
+                working: AtomicUsize::new(self.threads),
+                next_bound: Mutex::new(f32::INFINITY),
+                solutions: Mutex::new(Vec::new()),
+                expanded: AtomicU64::new(totals.expanded),
+                generated: AtomicU64::new(totals.generated),
+                depth: AtomicUsize::new(totals.depth),
+            };
+
+            let mut stats = shared.stats();
+            reporter.report(&mut stats);
+
+            let mut error = None;
+            thread::scope(|scope| {
+                for _ in 0..self.threads {
+                    let mut worker = Worker {
+                        heuristic: &self.heuristic,
+                        shared: &shared,
+                        index: 0,
+                        field: *start,
+                        path: Vec::new(),
+                        expanded: 0,
+                        generated: 0,
+                        depth: 0,
+                    };
+                    scope.spawn(move || worker.run());
+                }
+
+                // This thread keeps an eye on the budget and the progress reports
+                while shared.working.load(Ordering::Acquire) > 0 {
+                    thread::sleep(POLL_INTERVAL);
+                    let mut stats = shared.stats();
+                    if error.is_none() {
+                        if let Err(budget_error) = budget.check_all(&stats, memory) {
+                            error = Some(reporter.finish_error(budget_error));
+                            shared.stop.store(true, Ordering::Relaxed);
+                        }
+                    }
+                    reporter.poll(&mut stats);
+                }
+            });
+
+            totals = shared.stats();
+            // Every index below `found` was handed out before it, so it's the
+            // first solution unless the budget cut one of those short
+            let found = shared.found.load(Ordering::Relaxed);
+            if found != usize::MAX && found < shared.unfinished.load(Ordering::Relaxed) {
+                let solutions = shared.solutions.into_inner().unwrap();
+                let (_, moves) = solutions.into_iter().find(|(index, _)| *index == found).unwrap();
+                reporter.finish(&mut totals);
+                return Ok(AStarSolution::from_moves(start, moves, totals));
+            }
+            if let Some(error) = error { return Err(error) }
+
+            let next_bound = shared.next_bound.into_inner().unwrap();
+            if next_bound.is_infinite() { return Err(SolveError::Unsolvable) }
+            bound = next_bound;
+        }
+    }
+}
@@ -0,0 +1,367 @@
+use std::{fs, io, path::Path};
+use crate::field::{Field, Move, MAX_CELLS};
+use crate::rank;
+
+
+const MAGIC: &[u8; 4] = b"PDB\x1A";
+const VERSION: u32 = 2;
+const UNKNOWN: u8 = u8::MAX;
+
+pub const MAX_PATTERN_TILES: usize = 8;
+
+pub const PARTITION_6_6_3: [&[u8]; 3] = [&[1, 5, 6, 9, 10, 13], &[7, 8, 11, 12, 14, 15], &[2, 3, 4]];
+
+
+/// A reasonable partition for any board size: 6-6-3 on 4x4, otherwise the
+/// tiles in order, in groups small enough to build in a few seconds.
+///
+/// 6-6-3 builds in seconds and takes 11.5 MB, but leaves the hardest 4x4
+/// boards, around 80 moves, to minutes of search. Solving those in seconds
+/// takes the 7-8 partition, which `build` can't make: for the 8 tile table it
+/// would keep a visited bit per placement and blank region, about 1 GB next
+/// to the 519 MB table, and frontiers of several GB.
+pub fn default_partition(width: u8, height: u8) -> Vec<Vec<u8>> {
+    if width == 4 && height == 4 { return PARTITION_6_6_3.iter().map(|tiles| tiles.to_vec()).collect() }
+    let cells = width * height;
+    let group = if cells <= 16 { 6 } else { 4 };
+    (1..cells).collect::<Vec<u8>>().chunks(group).map(<[u8]>::to_vec).collect()
+}
+
+
+/// Index of a placement of the pattern's tiles into its table.
+fn rank(positions: &[u8], cells: u8) -> usize {
+    rank::lex_rank(positions, cells) as usize
+}
+
+/// Table size for `tiles` tiles on `cells` cells.
+fn entries(cells: usize, tiles: usize) -> usize {
+    rank::placements(cells, tiles) as usize
+}
+
+fn pack(positions: &[u8], blank: u8) -> u64 {
+    positions.iter().fold(blank as u64, |packed, &p| (packed << 5) | p as u64)
+}
+
+/// Where `cell` ends up when the board is mirrored about its main diagonal.
+fn transpose(cell: u8, width: u8) -> u8 {
+    (cell % width) * width + cell / width
+}
+
+/// What each tile is renamed to on the goal mirrored about its main
+/// diagonal, so it's the goal again. Only square goals with the blank on the
+/// diagonal mirror onto themselves like that.
+fn diagonal_relabelling(goal: &Field) -> Option<[u8; MAX_CELLS]> {
+    let (width, blank) = (goal.width(), goal.get_empty_index());
+    if width != goal.height() || transpose(blank, width) != blank { return None }
+    let mut relabelling = [0_u8; MAX_CELLS];
+    for cell in 0..goal.cell_count() {
+        relabelling[goal.get_linear(cell) as usize] = goal.get_linear(transpose(cell, width));
+    }
+    Some(relabelling)
+}
+
+fn unpack(mut packed: u64, positions: &mut [u8]) -> u8 {
+    for p in positions.iter_mut().rev() {
+        *p = (packed & 0x1F) as u8;
+        packed >>= 5;
+    }
+    packed as u8
+}
+
+
+struct Pattern {
+    tiles: Vec<u8>,
+    table: Vec<u8>,
+}
+
+impl Pattern {
+    fn lookup(&self, tile_positions: &[u8; MAX_CELLS], cells: u8) -> u8 {
+        let mut positions = [0_u8; MAX_PATTERN_TILES];
+        for (i, &tile) in self.tiles.iter().enumerate() {
+            positions[i] = tile_positions[tile as usize];
+        }
+        self.table[rank(&positions[..self.tiles.len()], cells)]
+    }
+
+    /// Retrograde breadth-first search from the goal board. Only moves of the
+    /// pattern's own tiles are counted, so disjoint patterns can be added up.
+    fn build(goal: &Field, tiles: &[u8]) -> Pattern {
+        let (width, cells) = (goal.width(), goal.cell_count());
+        let all_cells: u32 = (1 << cells) - 1;
+        let neighbours: Vec<u32> = (0..cells).map(|i| {
+            let mut mask = 0;
+            if i >= width { mask |= 1 << (i - width) }
+            if i + width < cells { mask |= 1 << (i + width) }
+            if i % width > 0 { mask |= 1 << (i - 1) }
+            if i % width < width - 1 { mask |= 1 << (i + 1) }
+            mask
+        }).collect();
+
+        // Cells the blank can reach without moving a pattern tile
+        let reachable = |occupied: u32, from: u8| -> u32 {
+            let free = all_cells & !occupied;
+            let mut region = 1_u32 << from;
+            loop {
+                let mut grown = region;
+                let mut rest = region;
+                while rest != 0 {
+                    grown |= neighbours[rest.trailing_zeros() as usize] & free;
+                    rest &= rest - 1;
+                }
+                if grown == region { return region }
+                region = grown;
+            }
+        };
+
+        let mut positions = goal.tile_positions(tiles);
+        let occupied = positions.iter().fold(0_u32, |mask, &p| mask | 1 << p);
+        let blank = reachable(occupied, goal.get_empty_index()).trailing_zeros() as u8;
+
+        let size = entries(cells as usize, tiles.len());
+        let mut table = vec![UNKNOWN; size];
+        // One bit per (placement, blank region), the region named by its lowest cell
+        let mut visited = vec![0_u64; (size * cells as usize).div_ceil(64)];
+        let mut visit = |index: usize, blank: u8| -> bool {
+            let bit = index * cells as usize + blank as usize;
+            let seen = visited[bit / 64] & (1 << (bit % 64)) != 0;
+            visited[bit / 64] |= 1 << (bit % 64);
+            !seen
+        };
+
+        let start = rank(&positions, cells);
+        visit(start, blank);
+        table[start] = 0;
+
+        let mut frontier = vec![pack(&positions, blank)];
+        let mut distance = 0;
+        while !frontier.is_empty() {
+            let mut next = Vec::new();
+            for &state in &frontier {
+                let blank = unpack(state, &mut positions);
+                let occupied = positions.iter().fold(0_u32, |mask, &p| mask | 1 << p);
+                let region = reachable(occupied, blank);
+
+                for i in 0..positions.len() {
+                    let from = positions[i];
+                    let mut targets = neighbours[from as usize] & region;
+                    while targets != 0 {
+                        let to = targets.trailing_zeros() as u8;
+                        targets &= targets - 1;
+
+                        positions[i] = to;
+                        let moved_occupied = (occupied & !(1 << from)) | 1 << to;
+                        let moved_blank = reachable(moved_occupied, from).trailing_zeros() as u8;
+                        let index = rank(&positions, cells);
+                        if visit(index, moved_blank) {
+                            if table[index] == UNKNOWN { table[index] = distance + 1 }
+                            next.push(pack(&positions, moved_blank));
+                        }
+                        positions[i] = from;
+                    }
+                }
+            }
+
+            frontier = next;
+            distance += 1;
+        }
+
+        Pattern { tiles: tiles.to_vec(), table }
+    }
+}
+
+
+/// Disjoint additive pattern database: the tiles are split into groups and
+/// the exact cost of solving each group on its own is summed up.
+///
+/// When the goal mirrors onto itself about the main diagonal, the mirrored
+/// board is looked up as well and the larger sum is used. It's just as far
+/// from the goal, and often gets a better estimate from the same tables.
+pub struct PatternDatabase {
+    goal: Field,
+    patterns: Vec<Pattern>,
+    pattern_of: [u8; MAX_CELLS],
+    reflection: Option<[u8; MAX_CELLS]>,
+}
+
+
+impl PatternDatabase {
+    pub fn build(width: u8, height: u8, partition: &[&[u8]]) -> PatternDatabase {
+        assert!(Field::is_valid_size(width, height), "unsupported board size {}x{}", width, height);
+        PatternDatabase::build_for_goal(&Field::with_size(width, height), partition)
+    }
+
+    /// Same as `build`, but measures the distance to `goal` instead of the solved board.
+    pub fn build_for_goal(goal: &Field, partition: &[&[u8]]) -> PatternDatabase {
+        let (width, height) = (goal.width(), goal.height());
+        let mut seen = [false; MAX_CELLS];
+        for &tile in partition.iter().flat_map(|tiles| tiles.iter()) {
+            assert!(tile > 0 && tile < width * height, "tile {} is not on a {}x{} board", tile, width, height);
+            assert!(!seen[tile as usize], "tile {} is in more than one pattern", tile);
+            seen[tile as usize] = true;
+        }
+        assert!(partition.iter().all(|tiles| !tiles.is_empty() && tiles.len() <= MAX_PATTERN_TILES), "patterns must have 1 to {} tiles", MAX_PATTERN_TILES);
+
+        let patterns = partition.iter().map(|tiles| Pattern::build(goal, tiles)).collect();
+        PatternDatabase::from_patterns(*goal, patterns)
+    }
+
+    fn from_patterns(goal: Field, patterns: Vec<Pattern>) -> PatternDatabase {
+        let mut pattern_of = [u8::MAX; MAX_CELLS];
+        for (i, pattern) in patterns.iter().enumerate() {
+            for &tile in &pattern.tiles {
+                pattern_of[tile as usize] = i as u8;
+            }
+        }
+        PatternDatabase { goal, patterns, pattern_of, reflection: diagonal_relabelling(&goal) }
+    }
+
+
+    pub fn width(&self) -> u8 {
+        self.goal.width()
+    }
+
+    pub fn height(&self) -> u8 {
+        self.goal.height()
+    }
+
+    pub fn goal(&self) -> &Field {
+        &self.goal
+    }
+
+    pub fn partition(&self) -> Vec<Vec<u8>> {
+        self.patterns.iter().map(|pattern| pattern.tiles.clone()).collect()
+    }
+
+    /// Whether the mirrored board is looked up too.
+    pub fn is_reflected(&self) -> bool {
+        self.reflection.is_some()
+    }
+
+
+    fn tile_positions(field: &Field) -> [u8; MAX_CELLS] {
+        let mut positions = [0_u8; MAX_CELLS];
+        for i in 0..field.cell_count() {
+            positions[field.get_linear(i) as usize] = i;
+        }
+        positions
+    }
+
+    fn sum(&self, positions: &[u8; MAX_CELLS], cells: u8) -> u32 {
+        self.patterns.iter().map(|pattern| pattern.lookup(positions, cells) as u32).sum()
+    }
+
+    pub fn evaluate(&self, field: &Field) -> f32 {
+        debug_assert!(field.width() == self.width() && field.height() == self.height());
+        let positions = PatternDatabase::tile_positions(field);
+        let cells = field.cell_count();
+        let mut h = self.sum(&positions, cells);
+        if let Some(relabelling) = &self.reflection {
+            let mut mirrored = [0_u8; MAX_CELLS];
+            for tile in 0..cells as usize {
+                mirrored[relabelling[tile] as usize] = transpose(positions[tile], field.width());
+            }
+            h = h.max(self.sum(&mirrored, cells));
+        }
+        h as f32
+    }
+
+    pub fn change(&self, field: &Field, m: Move) -> f32 {
+        // The larger of two sums doesn't change one lookup at a time
+        if self.is_reflected() {
+            let mut moved = *field;
+            moved.make_move(m);
+            return self.evaluate(&moved) - self.evaluate(field);
+        }
+
+        let piece_index = field.moved_piece_index(m).expect("invalid move");
+        let piece = field.get_linear(piece_index);
+        let Some(pattern) = self.patterns.get(self.pattern_of[piece as usize] as usize) else { return 0.0 };
+
+        let mut positions = PatternDatabase::tile_positions(field);
+        let cells = field.cell_count();
+        let before = pattern.lookup(&positions, cells) as f32;
+        positions[piece as usize] = field.get_empty_index();
+        pattern.lookup(&positions, cells) as f32 - before
+    }
+
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&[self.width(), self.height()]);
+        bytes.extend_from_slice(self.goal.get_string().as_bytes());
+        bytes.push(self.patterns.len() as u8);
+        for pattern in &self.patterns {
+            bytes.push(pattern.tiles.len() as u8);
+            bytes.extend_from_slice(&pattern.tiles);
+            bytes.extend_from_slice(&(pattern.table.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(&pattern.table);
+        }
+        fs::write(path, bytes)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<PatternDatabase> {
+        let bytes = fs::read(path)?;
+        let invalid = |reason: &str| io::Error::new(io::ErrorKind::InvalidData, format!("bad pattern database: {}", reason));
+
+        let mut cursor = bytes.as_slice();
+        let mut take = |n: usize| -> io::Result<&[u8]> {
+            if cursor.len() < n { return Err(invalid("unexpected end of file")) }
+            let (head, tail) = cursor.split_at(n);
+            cursor = tail;
+            Ok(head)
+        };
+
+        if take(4)? != MAGIC { return Err(invalid("wrong magic")) }
+        let version = u32::from_le_bytes(take(4)?.try_into().unwrap());
+        if version != VERSION { return Err(invalid(&format!("version {}, expected {}", version, VERSION))) }
+
+        let header = take(2)?;
+        let (width, height) = (header[0], header[1]);
+        if !Field::is_valid_size(width, height) { return Err(invalid("unsupported board size")) }
+        let cells = width * height;
+        let goal = std::str::from_utf8(take(cells as usize)?).ok()
+            .and_then(|goal| Field::from_string_sized(width, height, goal).ok())
+            .ok_or_else(|| invalid("goal board"))?;
+        let count = take(1)?[0];
+
+        let mut seen = [false; MAX_CELLS];
+        let mut patterns = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let length = take(1)?[0] as usize;
+            if length == 0 || length > MAX_PATTERN_TILES { return Err(invalid("pattern size")) }
+            let tiles = take(length)?.to_vec();
+            for &tile in &tiles {
+                if tile == 0 || tile >= cells || seen[tile as usize] { return Err(invalid("pattern tiles")) }
+                seen[tile as usize] = true;
+            }
+
+            let size = u64::from_le_bytes(take(8)?.try_into().unwrap()) as usize;
+            if size != entries(cells as usize, length) { return Err(invalid("table size")) }
+            let table = take(size)?.to_vec();
+            patterns.push(Pattern { tiles, table });
+        }
+
+        Ok(PatternDatabase::from_patterns(goal, patterns))
+    }
+
+    /// Loads the database from `path` if it holds the requested partition,
+    /// otherwise builds it and writes it there for the next run. A file that
+    /// can't be written doesn't stop the database from being used, the error
+    /// comes back alongside it.
+    pub fn load_or_build<P: AsRef<Path>>(path: P, width: u8, height: u8, partition: &[&[u8]]) -> (PatternDatabase, Option<io::Error>) {
+        PatternDatabase::load_or_build_for_goal(path, &Field::with_size(width, height), partition)
+    }
+
+    pub fn load_or_build_for_goal<P: AsRef<Path>>(path: P, goal: &Field, partition: &[&[u8]]) -> (PatternDatabase, Option<io::Error>) {
+        if let Ok(pdb) = PatternDatabase::load(&path) {
+            let same_partition = pdb.partition().iter().map(Vec::as_slice).eq(partition.iter().copied());
+            if pdb.goal == *goal && same_partition { return (pdb, None) }
+        }
+
+        let pdb = PatternDatabase::build_for_goal(goal, partition);
+        let error = pdb.save(&path).err();
+        (pdb, error)
+    }
+}
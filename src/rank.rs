@@ -0,0 +1,98 @@
+//! Perfect hashes for placements of distinct tiles on a board.
+//!
+//! A placement is the list of cells a set of tiles sit on, `positions[i]` being
+//! the cell of the i-th tile. Placing `k` tiles on `n` cells has
+//! `placements(n, k)` possible placements, and each order ranks them onto
+//! `0..placements(n, k)` with no gaps. Placing every tile (`k == n`) is a
+//! whole permutation.
+//!
+//! The lexicographic order keeps placements sorted, so ranks can be compared
+//! and ranges of them mean something. Myrvold–Ruskey is an arbitrary order,
+//! but ranks whole permutations in linear time instead of quadratic.
+
+
+/// Number of ways to place `k` distinct tiles on `n` cells, `n! / (n - k)!`.
+pub fn placements(n: usize, k: usize) -> u128 {
+    (0..k).map(|i| (n - i) as u128).product()
+}
+
+
+/// Rank of `positions` in lexicographic order.
+pub fn lex_rank(positions: &[u8], n: u8) -> u128 {
+    let mut used: u32 = 0;
+    let mut rank = 0;
+    for (i, &p) in positions.iter().enumerate() {
+        let used_below = (used & ((1 << p) - 1)).count_ones();
+        rank = rank * (n as usize - i) as u128 + (p as u32 - used_below) as u128;
+        used |= 1 << p;
+    }
+    rank
+}
+
+/// The `k` tile placement with lexicographic rank `rank`.
+pub fn lex_unrank(mut rank: u128, n: u8, k: usize) -> Vec<u8> {
+    // Digits come out last first, the i-th one in base n - i
+    let mut digits = vec![0_u8; k];
+    for i in (0..k).rev() {
+        let base = (n as usize - i) as u128;
+        digits[i] = (rank % base) as u8;
+        rank /= base;
+    }
+
+    let mut used: u32 = 0;
+    digits.iter().map(|&digit| {
+        // The digit-th cell not taken yet
+        let mut free = !used & ((1 << n) - 1);
+        for _ in 0..digit { free &= free - 1 }
+        let p = free.trailing_zeros() as u8;
+        used |= 1 << p;
+        p
+    }).collect()
+}
+
+
+/// Rank of `positions` in Myrvold–Ruskey order.
+///
+/// The tiles are read as the last `k` entries of a permutation of `0..n`,
+/// `positions[0]` last, with the rest filled in with whatever is left. Only
+/// those `k` entries decide the rank.
+pub fn mr_rank(positions: &[u8], n: u8) -> u128 {
+    let n = n as usize;
+    let mut permutation = [0_u8; 32];
+    let mut inverse = [u8::MAX; 32];
+    for (i, &p) in positions.iter().enumerate() {
+        permutation[n - 1 - i] = p;
+        inverse[p as usize] = (n - 1 - i) as u8;
+    }
+    let mut slot = 0;
+    for value in 0..n as u8 {
+        if inverse[value as usize] != u8::MAX { continue }
+        permutation[slot] = value;
+        inverse[value as usize] = slot as u8;
+        slot += 1;
+    }
+
+    let mut rank = 0;
+    let mut radix = 1;
+    for size in (n - positions.len() + 1..=n).rev() {
+        // Swap size - 1 into the last slot, what was there is the next digit
+        let digit = permutation[size - 1];
+        let other = inverse[size - 1] as usize;
+        permutation.swap(size - 1, other);
+        inverse.swap(digit as usize, size - 1);
+        rank += digit as u128 * radix;
+        radix *= size as u128;
+    }
+    rank
+}
+
+/// The `k` tile placement with Myrvold–Ruskey rank `rank`.
+pub fn mr_unrank(mut rank: u128, n: u8, k: usize) -> Vec<u8> {
+    let n = n as usize;
+    let mut permutation: Vec<u8> = (0..n as u8).collect();
+    for size in (n - k + 1..=n).rev() {
+        permutation.swap(size - 1, (rank % size as u128) as usize);
+        rank /= size as u128;
+    }
+    (0..k).map(|i| permutation[n - 1 - i]).collect()
+}
@@ -0,0 +1,105 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::astar::AStarSolution;
+use crate::field::{Field, Move};
+
+
+/// SplitMix64. Tiny and plenty random for shuffling boards, and the same seed
+/// always gives the same boards.
+#[derive(Clone, Debug)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng(seed)
+    }
+
+    /// Seeded from the clock, for when nobody asked for a particular seed.
+    pub fn from_time() -> Rng {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64);
+        Rng(nanos)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..n`.
+    pub fn below(&mut self, n: u64) -> u64 {
+        // Reject the top sliver that would make the low values more likely
+        let zone = u64::MAX - u64::MAX % n;
+        loop {
+            let x = self.next_u64();
+            if x < zone { return x % n }
+        }
+    }
+}
+
+
+/// `moves` random moves away from `goal`, never undoing the previous move.
+pub fn random_walk(goal: &Field, moves: usize, rng: &mut Rng) -> Field {
+    let mut field = *goal;
+    walk(&mut field, &mut None, moves, rng);
+    field
+}
+
+fn walk(field: &mut Field, previous: &mut Option<Move>, moves: usize, rng: &mut Rng) {
+    for _ in 0..moves {
+        let options: Vec<Move> = Move::iter()
+            .filter(|&m| field.is_valid_move(m) && *previous != Some(m.inverse()))
+            .collect();
+        let m = options[rng.below(options.len() as u64) as usize];
+        field.make_move(m);
+        *previous = Some(m);
+    }
+}
+
+
+/// Every board that can reach `goal` is equally likely. Shuffles all the
+/// cells, then swaps two tiles if that landed on the wrong parity. The swap
+/// pairs the unsolvable boards up with the solvable ones, so it keeps the odds even.
+pub fn uniform(goal: &Field, rng: &mut Rng) -> Field {
+    let mut cells = goal.cells();
+    for i in (1..cells.len()).rev() {
+        cells.swap(i, rng.below(i as u64 + 1) as usize);
+    }
+
+    let field = Field::from_cells(goal.width(), goal.height(), &cells).unwrap();
+    if field.is_solvable_toward(goal) { return field }
+    let tiles: Vec<usize> = (0..cells.len()).filter(|&i| cells[i] != 0).take(2).collect();
+    cells.swap(tiles[0], tiles[1]);
+    Field::from_cells(goal.width(), goal.height(), &cells).unwrap()
+}
+
+
+/// A board exactly `length` moves from `goal` when solved optimally, with
+/// `solve` as the oracle. It has to be optimal and solve toward `goal`.
+///
+/// Walks randomly away from the goal until the board is at least `length`
+/// moves out, then takes the board `length` moves before the end of its
+/// optimal solution. `None` if the walk gets nowhere near, which happens when
+/// `length` is more than the board's size allows.
+pub fn with_optimal_length<E>(
+    goal: &Field,
+    length: usize,
+    rng: &mut Rng,
+    mut solve: impl FnMut(&Field) -> Result<AStarSolution, E>,
+) -> Result<Option<Field>, E> {
+    let limit = 4 * length + 64;
+    let (mut field, mut previous) = (*goal, None);
+    let mut walked = length;
+    walk(&mut field, &mut previous, length, rng);
+    loop {
+        let solution = solve(&field)?;
+        let optimal = solution.moves.len();
+        if optimal >= length { return Ok(Some(solution.states[optimal - length])) }
+        if walked >= limit { return Ok(None) }
+
+        let moves = (length - optimal).max(2);
+        walk(&mut field, &mut previous, moves, rng);
+        walked += moves;
+    }
+}
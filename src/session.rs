@@ -0,0 +1,130 @@
+use std::{fmt, time::Duration};
+use crate::field::Field;
+
+
+/// One go at solving a board by hand: moves made, time taken and how it
+/// compares to the optimal solution. Knows nothing about windows, whoever
+/// runs the game tells it what happened.
+pub struct Session {
+    start: Field,
+    goal: Field,
+    moves: usize,
+    hints: usize,
+    elapsed: Duration,
+    optimal: Option<usize>,
+    victory: Option<Victory>,
+}
+
+
+/// How a solved session went.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Victory {
+    pub moves: usize,
+    pub hints: usize,
+    pub elapsed: Duration,
+    /// Fewest moves the start board takes, if it was found in time.
+    pub optimal: Option<usize>,
+}
+
+impl Victory {
+    /// Optimal moves over moves made, 1 for a perfect game.
+    pub fn efficiency(&self) -> Option<f32> {
+        self.optimal.map(|optimal| if self.moves == 0 { 1.0 } else { optimal as f32 / self.moves as f32 })
+    }
+}
+
+impl fmt::Display for Victory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Solved in {} moves and {}", self.moves, format_time(self.elapsed))?;
+        if let (Some(optimal), Some(efficiency)) = (self.optimal, self.efficiency()) {
+            write!(f, ", {} is optimal, {:.0}% efficient", optimal, efficiency * 100.0)?;
+        }
+        if self.hints > 0 { write!(f, ", {} hints", self.hints)? }
+        Ok(())
+    }
+}
+
+
+/// `m:ss.s`.
+pub fn format_time(time: Duration) -> String {
+    let tenths = (time.as_secs_f64() * 10.0).round() as u64;
+    format!("{}:{:02}.{}", tenths / 600, tenths / 10 % 60, tenths % 10)
+}
+
+
+impl Session {
+    /// A session of solving `start` toward `goal`. A start that's already
+    /// the goal is won from the outset, in no moves.
+    pub fn new(start: &Field, goal: &Field) -> Session {
+        let mut session = Session { start: *start, goal: *goal, moves: 0, hints: 0, elapsed: Duration::ZERO, optimal: None, victory: None };
+        if start == goal {
+            session.optimal = Some(0);
+            session.victory = Some(Victory { moves: 0, hints: 0, elapsed: Duration::ZERO, optimal: Some(0) });
+        }
+        session
+    }
+
+    pub fn start(&self) -> &Field {
+        &self.start
+    }
+
+    pub fn goal(&self) -> &Field {
+        &self.goal
+    }
+
+    pub fn moves(&self) -> usize {
+        self.moves
+    }
+
+    pub fn hints(&self) -> usize {
+        self.hints
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn optimal(&self) -> Option<usize> {
+        self.optimal
+    }
+
+    /// Set once the start board is solved optimally, which may take a while.
+    pub fn set_optimal(&mut self, optimal: usize) {
+        self.optimal = Some(optimal);
+        if let Some(victory) = &mut self.victory { victory.optimal = Some(optimal) }
+    }
+
+    /// Whether the clock is running. It starts with the first move and stops on the win.
+    pub fn is_running(&self) -> bool {
+        self.moves > 0 && self.victory.is_none()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.victory.is_some()
+    }
+
+    pub fn victory(&self) -> Option<&Victory> {
+        self.victory.as_ref()
+    }
+
+    /// Lets `time` pass on the clock, if it's running.
+    pub fn tick(&mut self, time: Duration) {
+        if self.is_running() { self.elapsed += time }
+    }
+
+    pub fn hint_used(&mut self) {
+        if !self.is_finished() { self.hints += 1 }
+    }
+
+    /// Counts `moves` that took the board to `board`. Returns the victory
+    /// the first time `board` is the goal, and nothing after that.
+    pub fn record(&mut self, moves: usize, board: &Field) -> Option<Victory> {
+        if self.is_finished() { return None }
+        self.moves += moves;
+        if *board != self.goal { return None }
+
+        let victory = Victory { moves: self.moves, hints: self.hints, elapsed: self.elapsed, optimal: self.optimal };
+        self.victory = Some(victory);
+        Some(victory)
+    }
+}
@@ -0,0 +1,282 @@
+use std::{fmt, sync::{Arc, atomic::{AtomicBool, Ordering}}, time::{Duration, Instant}};
+use crate::astar::AStarSolution;
+use crate::field::{Field, Move};
+
+
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct SearchStats {
+    pub expanded: u64,
+    pub generated: u64,
+    /// Largest open list (A*) or path (IDA*) held at once.
+    pub peak_open: usize,
+    /// Current f limit for IDA*, f of the node being expanded for A*. With an
+    /// admissible heuristic no solution is shorter than this.
+    pub bound: f32,
+    pub depth: usize,
+    pub elapsed: Duration,
+}
+
+
+pub type ProgressCallback = Arc<dyn Fn(&SearchStats) + Send + Sync>;
+
+
+/// Shared flag to stop a running search from another thread.
+#[derive(Clone, Default, Debug)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+
+#[derive(Clone)]
+pub struct SolveOptions {
+    /// Give up after expanding this many nodes.
+    pub max_nodes: Option<u64>,
+    pub max_time: Option<Duration>,
+    /// Rough cap in bytes on what the solver keeps around, mostly A*'s open and closed sets.
+    pub max_memory: Option<usize>,
+    pub cancel: Option<CancelToken>,
+    /// Called every `progress_interval` while searching and whenever IDA* raises its bound.
+    pub progress: Option<ProgressCallback>,
+    pub progress_interval: Duration,
+}
+
+impl Default for SolveOptions {
+    fn default() -> Self {
+        SolveOptions {
+            max_nodes: None,
+            max_time: None,
+            max_memory: None,
+            cancel: None,
+            progress: None,
+            progress_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+
+/// The limits from `SolveOptions` for one run of a solver.
+pub(crate) struct Budget {
+    max_nodes: Option<u64>,
+    deadline: Option<Instant>,
+    max_memory: Option<usize>,
+    cancel: Option<CancelToken>,
+}
+
+impl Budget {
+    pub(crate) fn new(options: &SolveOptions) -> Budget {
+        Budget {
+            max_nodes: options.max_nodes,
+            deadline: options.max_time.map(|max_time| Instant::now() + max_time),
+            max_memory: options.max_memory,
+            cancel: options.cancel.clone(),
+        }
+    }
+
+    pub(crate) fn unlimited() -> Budget {
+        Budget { max_nodes: None, deadline: None, max_memory: None, cancel: None }
+    }
+
+    /// Called once per expansion with the solver's current memory estimate.
+    /// The clock is only read every 1024 expansions.
+    pub(crate) fn check(&self, stats: &SearchStats, memory: usize) -> Result<(), SolveError> {
+        if self.max_nodes.is_some_and(|max_nodes| stats.expanded >= max_nodes) { return Err(SolveError::NodeLimit(*stats)) }
+        if self.max_memory.is_some_and(|max_memory| memory >= max_memory) { return Err(SolveError::MemoryLimit(*stats)) }
+        if self.cancel.as_ref().is_some_and(CancelToken::is_cancelled) { return Err(SolveError::Cancelled(*stats)) }
+        if stats.expanded.is_multiple_of(1024) { self.check_time(stats)? }
+        Ok(())
+    }
+
+    /// Same as `check`, but always looks at the clock.
+    pub(crate) fn check_all(&self, stats: &SearchStats, memory: usize) -> Result<(), SolveError> {
+        self.check(stats, memory)?;
+        self.check_time(stats)
+    }
+
+    fn check_time(&self, stats: &SearchStats) -> Result<(), SolveError> {
+        if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) { return Err(SolveError::TimeLimit(*stats)) }
+        Ok(())
+    }
+}
+
+
+/// Keeps the clock for a search and fires the progress callback when it's due.
+pub(crate) struct ProgressReporter {
+    callback: Option<ProgressCallback>,
+    interval: Duration,
+    started: Instant,
+    last_report: Instant,
+}
+
+impl ProgressReporter {
+    pub(crate) fn new(options: &SolveOptions) -> ProgressReporter {
+        let now = Instant::now();
+        ProgressReporter {
+            callback: options.progress.clone(),
+            interval: options.progress_interval,
+            started: now,
+            last_report: now,
+        }
+    }
+
+    pub(crate) fn restart(&mut self) {
+        self.started = Instant::now();
+        self.last_report = self.started;
+    }
+
+    /// Cheap enough to call on every expansion, only looks at the clock every so often.
+    pub(crate) fn tick(&mut self, stats: &mut SearchStats) {
+        if !stats.expanded.is_multiple_of(1024) { return }
+        self.poll(stats);
+    }
+
+    /// Reports if the interval has passed since the last report.
+    pub(crate) fn poll(&mut self, stats: &mut SearchStats) {
+        if self.callback.is_none() { return }
+        if self.last_report.elapsed() >= self.interval {
+            self.report(stats);
+        }
+    }
+
+    pub(crate) fn report(&mut self, stats: &mut SearchStats) {
+        self.finish(stats);
+        self.last_report = Instant::now();
+        if let Some(callback) = &self.callback {
+            callback(stats);
+        }
+    }
+
+    pub(crate) fn finish(&self, stats: &mut SearchStats) {
+        stats.elapsed = self.started.elapsed();
+    }
+
+    pub(crate) fn finish_error(&self, mut error: SolveError) -> SolveError {
+        if let SolveError::NodeLimit(stats) | SolveError::TimeLimit(stats)
+            | SolveError::MemoryLimit(stats) | SolveError::Cancelled(stats) = &mut error {
+            self.finish(stats);
+        }
+        error
+    }
+}
+
+
+/// Why a solver stopped without a solution. Budget and cancellation errors
+/// carry the search statistics at the moment it stopped.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SolveError {
+    Unsolvable,
+    NodeLimit(SearchStats),
+    TimeLimit(SearchStats),
+    MemoryLimit(SearchStats),
+    Cancelled(SearchStats),
+}
+
+impl SolveError {
+    pub fn stats(&self) -> Option<&SearchStats> {
+        match self {
+            SolveError::Unsolvable => None,
+            SolveError::NodeLimit(stats) | SolveError::TimeLimit(stats)
+                | SolveError::MemoryLimit(stats) | SolveError::Cancelled(stats) => Some(stats),
+        }
+    }
+
+    /// Fewest moves a solution can have, as far as the search got to prove.
+    pub fn lower_bound(&self) -> Option<f32> {
+        self.stats().map(|stats| stats.bound)
+    }
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match self {
+            SolveError::Unsolvable => "field isn't solvable",
+            SolveError::NodeLimit(_) => "node limit reached",
+            SolveError::TimeLimit(_) => "time limit reached",
+            SolveError::MemoryLimit(_) => "memory limit reached",
+            SolveError::Cancelled(_) => "cancelled",
+        })?;
+
+        if let Some(stats) = self.stats() {
+            write!(f, " after {} nodes, solution needs at least {} moves", stats.expanded, stats.bound)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for SolveError {}
+
+
+/// What's wrong with a move sequence that was supposed to solve a board.
+#[derive(Clone, Copy, PartialEq)]
+pub enum VerifyError {
+    /// Move `index` (from 0) can't be made on `board`, the board before it.
+    IllegalMove { index: usize, m: Move, board: Field },
+    /// Every move is legal, but they end on `board` instead of the goal.
+    NotSolved { board: Field },
+    /// A solution's state `index` isn't where its moves lead.
+    WrongState { index: usize },
+}
+
+impl VerifyError {
+    /// Index of the first move that went wrong, if it was a move.
+    pub fn bad_move(&self) -> Option<usize> {
+        match self {
+            VerifyError::IllegalMove { index, .. } => Some(*index),
+            _ => None,
+        }
+    }
+
+    /// Board the moves got stuck on or ended on.
+    pub fn board(&self) -> Option<Field> {
+        match self {
+            VerifyError::IllegalMove { board, .. } | VerifyError::NotSolved { board } => Some(*board),
+            VerifyError::WrongState { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::IllegalMove { index, m, board } => write!(f, "move {} ({}) can't be made on {}", index + 1, m, board.get_string()),
+            VerifyError::NotSolved { board } => write!(f, "the moves end on {} instead of the goal", board.get_string()),
+            VerifyError::WrongState { index } => write!(f, "state {} doesn't follow from the moves", index),
+        }
+    }
+}
+
+
+/// Applies `moves` to `start` one by one and returns every board along the
+/// way, `start` first, or the first move that can't be made.
+pub fn replay(start: &Field, moves: &[Move]) -> Result<Vec<Field>, VerifyError> {
+    let mut board = *start;
+    let mut states = vec![board];
+    for (index, &m) in moves.iter().enumerate() {
+        if !board.make_move(m) { return Err(VerifyError::IllegalMove { index, m, board }) }
+        states.push(board);
+    }
+    Ok(states)
+}
+
+/// Checks that `moves` are all legal from `start` and end on `goal`.
+pub fn verify(start: &Field, moves: &[Move], goal: &Field) -> Result<(), VerifyError> {
+    let board = *replay(start, moves)?.last().unwrap();
+    if board != *goal { return Err(VerifyError::NotSolved { board }) }
+    Ok(())
+}
+
+
+pub trait Solver {
+    fn solve(&mut self, start: &Field, options: &SolveOptions) -> Result<AStarSolution, SolveError>;
+}
@@ -1,6 +1,7 @@
 #[cfg(test)]
-
 use super::*;
+#[cfg(test)]
+use crate::astar::AStarSolution;
 
 
 
@@ -8,16 +9,17 @@ use super::*;
 macro_rules! assert_calculate_heuristic {
     ($s:expr, $v:expr) => {
         {
-            let field = field::Field::from($s).ok().unwrap();
+            let s: u64 = $s;
+            let field = field::Field::from(s).ok().unwrap();
             let result: f32 = astar::calculate_heuristic(&field);
-            assert_eq!(result, $v as f32, "{} is {}, should be {}", $s, result, $v as f32);
+            assert_eq!(result, $v as f32, "{:#018X} is {}, should be {}", s, result, $v as f32);
         }
     };
 }
 
 #[test]
 fn test_calculate_heuristic() {
-    assert_calculate_heuristic!(0x5134207896ACDEBF, 12);
+    assert_calculate_heuristic!(0x5134207896ACDEBF, 8);
 }
 
 
@@ -26,29 +28,30 @@ fn test_calculate_heuristic() {
 macro_rules! assert_calculate_heuristic_change {
     ($s:expr, $m:expr, $v:expr) => {
         {
-            let field = field::Field::from($s).ok().unwrap();
+            let s: u64 = $s;
+            let field = field::Field::from(s).ok().unwrap();
             let result: f32 = astar::calculate_heuristic_change(&field, $m);
-            assert_eq!(result, $v as f32, "{} is {}, should be {}", $s, result, $v as f32);
+            assert_eq!(result, $v as f32, "{:#018X} is {}, should be {}", s, result, $v as f32);
         }
     };
 }
 
 #[test]
 fn test_calculate_heuristic_change() {
-    assert_calculate_heuristic_change!(0x5134207896ACDEBF, field::Move::Up, 2);
-    assert_calculate_heuristic_change!(0x5134207896ACDEBF, field::Move::Down, -2);
-    assert_calculate_heuristic_change!(0x5134207896ACDEBF, field::Move::Left, 0);
-    assert_calculate_heuristic_change!(0x5134207896ACDEBF, field::Move::Right, 0);
+    assert_calculate_heuristic_change!(0x5134207896ACDEBF, field::Move::Up, 1);
+    assert_calculate_heuristic_change!(0x5134207896ACDEBF, field::Move::Down, -1);
+    assert_calculate_heuristic_change!(0x5134207896ACDEBF, field::Move::Left, -1);
+    assert_calculate_heuristic_change!(0x5134207896ACDEBF, field::Move::Right, 1);
 
-    assert_calculate_heuristic_change!(0x1723068459ACDEBF, field::Move::Up, 2);
-    assert_calculate_heuristic_change!(0x1723068459ACDEBF, field::Move::Down, -2);
-    assert_calculate_heuristic_change!(0x1723068459ACDEBF, field::Move::Right, 0);
+    assert_calculate_heuristic_change!(0x1723068459ACDEBF, field::Move::Up, 1);
+    assert_calculate_heuristic_change!(0x1723068459ACDEBF, field::Move::Down, -1);
+    assert_calculate_heuristic_change!(0x1723068459ACDEBF, field::Move::Right, 1);
 
-    assert_calculate_heuristic_change!(0x123456789ABCDEF0, field::Move::Up, 2);
-    assert_calculate_heuristic_change!(0x123456789ABCDEF0, field::Move::Left, 2);
+    assert_calculate_heuristic_change!(0x123456789ABCDEF0, field::Move::Up, 1);
+    assert_calculate_heuristic_change!(0x123456789ABCDEF0, field::Move::Left, 1);
 
-    assert_calculate_heuristic_change!(0x12345678EABC0D9F, field::Move::Up, 0);
-    assert_calculate_heuristic_change!(0x12345678EABC0D9F, field::Move::Right, -2);
+    assert_calculate_heuristic_change!(0x12345678EABC0D9F, field::Move::Up, -1);
+    assert_calculate_heuristic_change!(0x12345678EABC0D9F, field::Move::Right, -1);
 }
 
 
@@ -57,9 +60,10 @@ fn test_calculate_heuristic_change() {
 macro_rules! is_solvable {
     ($s:expr, $b:expr) => {
         {
-            let field = field::Field::from($s).ok().unwrap();
+            let s: u64 = $s;
+            let field = field::Field::from(s).ok().unwrap();
             let result: bool = field.is_solvable();
-            assert_eq!(result, $b, "{} is {}, should be {}", $s, result, $b);
+            assert_eq!(result, $b, "{:#018X} is {}, should be {}", s, result, $b);
         }
     };
 }
@@ -92,7 +96,8 @@ fn test_is_solvable() {
 macro_rules! solve {
     ($s:expr, $b:expr) => {
         {
-            let field = field::Field::from($s).ok().unwrap();
+            let s: u64 = $s;
+            let field = field::Field::from(s).ok().unwrap();
             let mut star = astar::AStar::new(&field);
             let mut result_option: Option<Result<AStarSolution, ()>> = Default::default();
             while result_option.is_none() {
@@ -101,7 +106,7 @@ macro_rules! solve {
 
             let solution = result_option.unwrap().ok().unwrap();
             let length = solution.moves.len();
-            assert_eq!(length, $b, "{} is {}, should be {}", $s, length, $b);
+            assert_eq!(length, $b, "{:#018X} is {}, should be {}", s, length, $b);
         }
     };
 }
@@ -123,4 +128,1038 @@ fn test_solver() {
     // solve!(0xD79F2E8A45106C3B, 55);
     // solve!(0xDBE87A2C91F65034, 58);
     // solve!(0xBAC0F478E19623D5, 61);
-}
\ No newline at end of file
+}
+
+
+#[allow(unused_macros)]
+macro_rules! solve_sized {
+    ($w:expr, $h:expr, $s:expr, $b:expr) => {
+        {
+            let field = field::Field::from_string_sized($w, $h, $s).ok().unwrap();
+            assert!(field.is_solvable(), "{} should be solvable", $s);
+            let mut star = astar::AStar::new(&field);
+            let mut result_option: Option<Result<AStarSolution, ()>> = Default::default();
+            while result_option.is_none() {
+                result_option = star.step();
+            }
+
+            let solution = result_option.unwrap().ok().unwrap();
+            let length = solution.moves.len();
+            assert_eq!(length, $b, "{} is {}, should be {}", $s, length, $b);
+
+            let mut idastar = astar::IDAStar::new(&field);
+            let length = idastar.run().ok().unwrap().moves.len();
+            assert_eq!(length, $b, "{} is {} with IDA*, should be {}", $s, length, $b);
+        }
+    };
+}
+
+#[test]
+fn test_field_sizes() {
+    let field = field::Field::with_size(3, 3);
+    assert!(field.is_solved());
+    assert_eq!(field.get_string(), "123456780");
+    assert_eq!(field::Field::from_string("123456780").ok().unwrap().width(), 3);
+
+    let field = field::Field::with_size(5, 5);
+    assert!(field.is_solved());
+    assert_eq!(field.get_string(), "123456789ABCDEFGHIJKLMNO0");
+    assert!(field::Field::from_string(field.get_string()).ok().unwrap() == field);
+
+    let field = field::Field::from_string_sized(3, 5, "123456789ABCDE0").ok().unwrap();
+    assert!(field.is_solved());
+    assert_eq!(field::Field::new().get_string(), "123456789ABCDEF0");
+
+    assert!(field::Field::from_string_sized(2, 4, "1234567").is_err());
+    assert!(field::Field::from_string_sized(2, 4, "12345677").is_err());
+    assert!(field::Field::from_string("12345678").is_err());
+}
+
+#[test]
+fn test_is_solvable_sized() {
+    assert!(field::Field::from_string("123456708").ok().unwrap().is_solvable());
+    assert!(!field::Field::from_string("123456870").ok().unwrap().is_solvable());
+    assert!(field::Field::from_string_sized(2, 4, "12345670").ok().unwrap().is_solvable());
+    assert!(!field::Field::from_string_sized(2, 4, "12345760").ok().unwrap().is_solvable());
+    assert!(field::Field::from_string_sized(4, 2, "12340567").ok().unwrap().is_solvable());
+    assert!(!field::Field::from_string_sized(4, 2, "12340576").ok().unwrap().is_solvable());
+    assert!(!field::Field::from_string("123456789ABCDEFGHIJKLMON0").ok().unwrap().is_solvable());
+}
+
+#[test]
+fn test_calculate_heuristic_sized() {
+    let field = field::Field::from_string("123456789ABCDEFGHIJK0LMNO").ok().unwrap();
+    assert_eq!(astar::calculate_heuristic(&field), 4.0);
+    assert_eq!(astar::calculate_heuristic_change(&field, field::Move::Right), -1.0);
+    assert_eq!(astar::calculate_heuristic_change(&field, field::Move::Up), 1.0);
+}
+
+#[test]
+fn test_solver_sized() {
+    solve_sized!(3, 3, "123456780", 0);
+    solve_sized!(3, 3, "123405786", 2);
+    solve_sized!(3, 3, "867254301", 31);
+    solve_sized!(2, 4, "12345670", 0);
+    solve_sized!(2, 4, "71563024", 27);
+    solve_sized!(4, 2, "12340567", 3);
+    solve_sized!(3, 5, "123456789AB0DEC", 1);
+}
+
+
+
+#[test]
+fn test_linear_conflict() {
+    // 2 and 1 swapped in their goal row
+    let field = field::Field::from(0x213456789ABCDEF0).ok().unwrap();
+    assert_eq!(astar::calculate_linear_conflict(&field), 4.0);
+
+    // 5 and 1 swapped in their goal column
+    let field = field::Field::from(0x523416789ABCDEF0).ok().unwrap();
+    assert_eq!(astar::calculate_linear_conflict(&field), 4.0);
+
+    // 3 2 1 only needs two of the tiles moved out of the row
+    let field = field::Field::from_string("321456780").ok().unwrap();
+    assert_eq!(astar::calculate_linear_conflict(&field), 8.0);
+
+    let field = field::Field::new();
+    assert_eq!(astar::calculate_linear_conflict(&field), 0.0);
+}
+
+#[test]
+fn test_linear_conflict_change() {
+    let boards = [
+        "5134207896ACDEBF", "1723068459ACDEBF", "12345678EABC0D9F", "75AB2C416D389F0E",
+        "04582E1DF79BCA36", "FE169B4C0A73D852", "D79F2E8A45106C3B", "867254301", "123456789ABCDEFGHIJKLMN0O",
+    ];
+    for board in boards {
+        let field = field::Field::from_string(board).ok().unwrap();
+        for m in field::Move::iter().filter(|&m| field.is_valid_move(m)) {
+            let mut moved = field;
+            moved.make_move(m);
+            let expected = astar::calculate_linear_conflict(&moved) - astar::calculate_linear_conflict(&field);
+            let result = astar::calculate_linear_conflict_change(&field, m);
+            assert_eq!(result, expected, "{} {} is {}, should be {}", board, m, result, expected);
+        }
+    }
+}
+
+
+#[allow(unused_macros)]
+macro_rules! solve_linear_conflict {
+    ($s:expr, $b:expr) => {
+        {
+            let s: u64 = $s;
+            let field = field::Field::from(s).ok().unwrap();
+            let mut idastar = astar::IDAStar::with_heuristic(&field, heuristic::LinearConflict);
+            let length = idastar.run().ok().unwrap().moves.len();
+            assert_eq!(length, $b, "{:#018X} is {}, should be {}", s, length, $b);
+        }
+    };
+}
+
+#[test]
+fn test_solver_linear_conflict() {
+    solve_linear_conflict!(0x123456789ABCDEF0,  0);
+    solve_linear_conflict!(0x5134207896ACDEBF,  8);
+    solve_linear_conflict!(0x12345678A0BE9FCD, 19);
+    solve_linear_conflict!(0x75123804A6BE9FCD, 35);
+    solve_linear_conflict!(0x75AB2C416D389F0E, 45);
+    solve_linear_conflict!(0x04582E1DF79BCA36, 48);
+
+    let field = field::Field::from(0x51247308A6BE9FCD).ok().unwrap();
+    let mut star = astar::AStar::with_heuristic(&field, heuristic::LinearConflict);
+    let mut result_option: Option<Result<AStarSolution, ()>> = Default::default();
+    while result_option.is_none() {
+        result_option = star.step();
+    }
+    assert_eq!(result_option.unwrap().ok().unwrap().moves.len(), 27);
+}
+
+
+
+#[test]
+fn test_pattern_database() {
+    let pdb = pdb::PatternDatabase::build(3, 3, &[&[1, 2, 3, 4], &[5, 6, 7, 8]]);
+    assert_eq!(pdb.evaluate(&field::Field::with_size(3, 3)), 0.0);
+
+    let boards = ["123456708", "123405786", "867254301", "647850321", "806547231", "012345678"];
+    for board in boards {
+        let field = field::Field::from_string(board).ok().unwrap();
+        let value = pdb.evaluate(&field);
+        assert!(value >= astar::calculate_heuristic(&field), "{} is {}, below Manhattan distance", board, value);
+
+        for m in field::Move::iter().filter(|&m| field.is_valid_move(m)) {
+            let mut moved = field;
+            moved.make_move(m);
+            assert_eq!(pdb.change(&field, m), pdb.evaluate(&moved) - value, "{} {}", board, m);
+        }
+    }
+
+    // A board mirrored about the diagonal, tiles renamed, is just as far from solved
+    assert!(pdb.is_reflected());
+    assert!(!pdb::PatternDatabase::build(3, 2, &[&[1, 2, 3, 4, 5]]).is_reflected());
+    let transpose = |cell: u8| (cell % 3) * 3 + cell / 3;
+    for board in boards {
+        let field = field::Field::from_string(board).ok().unwrap();
+        let mut cells = [0_u8; 9];
+        for cell in 0..9 {
+            let tile = field.get_linear(cell);
+            cells[transpose(cell) as usize] = if tile == 0 { 0 } else { transpose(tile - 1) + 1 };
+        }
+        let mirrored = field::Field::from_cells(3, 3, &cells).unwrap();
+        assert_eq!(pdb.evaluate(&mirrored), pdb.evaluate(&field), "{}", board);
+    }
+
+    let path = std::env::temp_dir().join(format!("pyatnahi_test_{}.bin", std::process::id()));
+    pdb.save(&path).unwrap();
+    let loaded = pdb::PatternDatabase::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.partition(), vec![vec![1, 2, 3, 4], vec![5, 6, 7, 8]]);
+    for board in boards {
+        let field = field::Field::from_string(board).ok().unwrap();
+        assert_eq!(loaded.evaluate(&field), pdb.evaluate(&field));
+    }
+
+    // A file that can't be written still leaves a database to use
+    let (built, error) = pdb::PatternDatabase::load_or_build(std::env::temp_dir(), 3, 2, &[&[1, 2, 3, 4, 5]]);
+    assert!(error.is_some());
+    assert_eq!(built.partition(), vec![vec![1, 2, 3, 4, 5]]);
+}
+
+#[test]
+fn test_solver_pattern_database() {
+    let pdb = std::sync::Arc::new(pdb::PatternDatabase::build(3, 3, &[&[1, 2, 3, 4], &[5, 6, 7, 8]]));
+    for (board, length) in [("123456780", 0), ("123405786", 2), ("867254301", 31), ("647850321", 31)] {
+        let field = field::Field::from_string(board).ok().unwrap();
+        let mut idastar = astar::IDAStar::with_heuristic(&field, pdb.clone());
+        let result = idastar.run().ok().unwrap().moves.len();
+        assert_eq!(result, length, "{} is {}, should be {}", board, result, length);
+    }
+}
+
+
+
+#[test]
+fn test_custom_heuristic() {
+    use heuristic::Heuristic;
+
+    struct Misplaced;
+
+    impl heuristic::Heuristic for Misplaced {
+        fn evaluate(&self, field: &field::Field) -> f32 {
+            (0..field.cell_count()).filter(|&i| {
+                let piece = field.get_linear(i);
+                piece != 0 && piece != i + 1
+            }).count() as f32
+        }
+
+        fn is_admissible(&self) -> bool {
+            true
+        }
+    }
+
+    let field = field::Field::from(0x1723068459ACDEBF).ok().unwrap();
+    let mut idastar = astar::IDAStar::with_heuristic(&field, Misplaced);
+    assert_eq!(idastar.run().ok().unwrap().moves.len(), 13);
+
+    let boxed: Box<dyn heuristic::Heuristic> = Box::new(heuristic::LinearConflict);
+    let mut idastar = astar::IDAStar::with_heuristic(&field, boxed);
+    assert_eq!(idastar.run().ok().unwrap().moves.len(), 13);
+
+    let weighted = heuristic::Weighted::new(heuristic::Manhattan, 2.0);
+    assert!(!weighted.is_admissible());
+    assert!(heuristic::Weighted::new(heuristic::Manhattan, 0.5).is_admissible());
+    assert_eq!(weighted.evaluate(&field), 2.0 * astar::calculate_heuristic(&field));
+    assert_eq!(weighted.next(&field, weighted.evaluate(&field), field::Move::Up), 2.0 * (astar::calculate_heuristic(&field) + 1.0));
+
+    let mut star = astar::AStar::with_heuristic(&field, weighted);
+    let mut result_option: Option<Result<AStarSolution, ()>> = Default::default();
+    while result_option.is_none() {
+        result_option = star.step();
+    }
+    assert!(result_option.unwrap().ok().unwrap().moves.len() >= 13);
+}
+
+
+
+#[test]
+fn test_ida_solution_states() {
+    let field = field::Field::from(0x51247308A6BE9FCD).ok().unwrap();
+    let mut idastar = astar::IDAStar::new(&field);
+    let solution = idastar.run().ok().unwrap();
+    assert_eq!(solution.moves.len(), 27);
+    assert_eq!(solution.states.len(), 28);
+    assert!(solution.states[0] == field);
+    assert!(solution.states.last().unwrap().is_solved());
+
+    let mut replay = field;
+    for (m, state) in solution.moves.iter().zip(solution.states.iter().skip(1)) {
+        assert!(replay.make_move(*m));
+        assert!(replay == *state);
+    }
+
+    // Running again starts over from the same board
+    assert_eq!(idastar.run().ok().unwrap().moves, solution.moves);
+}
+
+
+
+#[test]
+fn test_solver_trait() {
+    use solver::{SolveError, SolveOptions, Solver};
+
+    let field = field::Field::from(0x12345678A0BE9FCD).ok().unwrap();
+    let unsolvable = field::Field::from(0x123456789AFB0EDC).ok().unwrap();
+    let limited = SolveOptions { max_nodes: Some(10), ..Default::default() };
+
+    let mut solvers: Vec<Box<dyn Solver>> = vec![
+        Box::new(astar::AStar::new(&field::Field::new())),
+        Box::new(astar::IDAStar::with_heuristic(&field::Field::new(), heuristic::LinearConflict)),
+    ];
+    for solver in solvers.iter_mut() {
+        assert_eq!(solver.solve(&field, &SolveOptions::default()).ok().unwrap().moves.len(), 19);
+        assert_eq!(solver.solve(&field::Field::new(), &SolveOptions::default()).ok().unwrap().moves.len(), 0);
+        assert_eq!(solver.solve(&unsolvable, &SolveOptions::default()).err(), Some(SolveError::Unsolvable));
+        assert!(matches!(solver.solve(&field, &limited), Err(SolveError::NodeLimit(_))));
+    }
+}
+
+
+
+#[test]
+fn test_search_stats() {
+    use solver::{SearchStats, SolveOptions, Solver};
+    use std::sync::{Arc, Mutex};
+
+    let field = field::Field::from(0x51247308A6BE9FCD).ok().unwrap();
+    let reports: Arc<Mutex<Vec<SearchStats>>> = Default::default();
+    let reports_sink = reports.clone();
+    let options = SolveOptions {
+        progress: Some(Arc::new(move |stats: &SearchStats| reports_sink.lock().unwrap().push(*stats))),
+        ..Default::default()
+    };
+
+    let mut idastar = astar::IDAStar::new(&field);
+    let solution = idastar.solve(&field, &options).ok().unwrap();
+    assert_eq!(solution.stats.bound, 27.0);
+    assert_eq!(solution.stats.depth, 26);
+    assert!(solution.stats.generated >= solution.stats.expanded && solution.stats.expanded > 0);
+
+    // One report per threshold, starting at the root's estimate
+    let bounds: Vec<f32> = reports.lock().unwrap().iter().map(|stats| stats.bound).collect();
+    assert_eq!(bounds.first(), Some(&astar::calculate_heuristic(&field)));
+    assert_eq!(bounds.last(), Some(&27.0));
+    assert!(bounds.windows(2).all(|pair| pair[0] < pair[1]));
+
+    let mut star = astar::AStar::new(&field);
+    let solution = star.solve(&field, &SolveOptions::default()).ok().unwrap();
+    assert_eq!(solution.stats.bound, 27.0);
+    assert_eq!(solution.stats.depth, 27);
+    assert!(solution.stats.peak_open > 0 && solution.stats.generated >= solution.stats.expanded);
+}
+
+
+
+#[test]
+fn test_solve_budgets() {
+    use solver::{CancelToken, SolveError, SolveOptions, Solver};
+    use std::time::Duration;
+
+    let field = field::Field::from(0xBAC0F478E19623D5).ok().unwrap();
+
+    let cancel = CancelToken::new();
+    cancel.cancel();
+    let cancelled = SolveOptions { cancel: Some(cancel), ..Default::default() };
+    let timed = SolveOptions { max_time: Some(Duration::from_millis(50)), ..Default::default() };
+    let small = SolveOptions { max_memory: Some(1 << 20), ..Default::default() };
+
+    let mut idastar = astar::IDAStar::new(&field);
+    assert!(matches!(idastar.solve(&field, &cancelled), Err(SolveError::Cancelled(_))));
+
+    let error = idastar.solve(&field, &timed).err().unwrap();
+    assert!(matches!(error, SolveError::TimeLimit(_)), "{}", error);
+    // The lower bound only ever grows from the root estimate towards the real length
+    let lower_bound = error.lower_bound().unwrap();
+    assert!(lower_bound >= astar::calculate_heuristic(&field) && lower_bound <= 61.0);
+    assert!(error.stats().unwrap().elapsed >= Duration::from_millis(50));
+
+    let mut star = astar::AStar::new(&field);
+    assert!(matches!(star.solve(&field, &cancelled), Err(SolveError::Cancelled(_))));
+    let error = star.solve(&field, &small).err().unwrap();
+    assert!(matches!(error, SolveError::MemoryLimit(_)), "{}", error);
+    assert!(star.memory_usage() >= 1 << 20);
+}
+
+
+
+#[test]
+fn test_parallel_idastar() {
+    use parallel::ParallelIDAStar;
+    use solver::{SolveError, SolveOptions, Solver};
+
+    let unsolvable = field::Field::from(0x123456789AFB0EDC).ok().unwrap();
+    let cancel = solver::CancelToken::new();
+    cancel.cancel();
+    let cancelled = SolveOptions { cancel: Some(cancel), ..Default::default() };
+
+    for s in [0x12345678A0BE9FCD, 0x51247308A6BE9FCD, 0x123456789ABCDEF0, 0x123456789ABC0DEF] {
+        let field = field::Field::from(s).ok().unwrap();
+        let serial = astar::IDAStar::with_heuristic(&field, heuristic::LinearConflict).run().ok().unwrap();
+
+        // Same optimal answer as the serial search whatever the thread count and split depth
+        for threads in [1, 2, 8] {
+            let mut parallel = ParallelIDAStar::with_heuristic(heuristic::LinearConflict).with_threads(threads);
+            for _ in 0..2 {
+                let solution = parallel.solve(&field, &SolveOptions::default()).ok().unwrap();
+                assert_eq!(solution.moves, serial.moves, "{:#018X} on {} threads", s, threads);
+                assert!(solution.states.last().unwrap().is_solved());
+            }
+        }
+        let mut shallow = ParallelIDAStar::with_heuristic(heuristic::LinearConflict).with_frontier_depth(2);
+        assert_eq!(shallow.solve(&field, &SolveOptions::default()).ok().unwrap().moves, serial.moves);
+    }
+
+    // Cut short anywhere, it either gives up or still has the serial answer
+    let field = field::Field::from(0x51247308A6BE9FCD).ok().unwrap();
+    let serial = astar::IDAStar::with_heuristic(&field, heuristic::Manhattan).run().ok().unwrap();
+    let mut parallel = ParallelIDAStar::new().with_threads(8).with_frontier_depth(3);
+    for max_nodes in [1, 100, 1_000, 3_000, 10_000, 30_000] {
+        match parallel.solve(&field, &SolveOptions { max_nodes: Some(max_nodes), ..Default::default() }) {
+            Ok(solution) => assert_eq!(solution.moves, serial.moves, "{} nodes", max_nodes),
+            Err(error) => assert!(matches!(error, SolveError::NodeLimit(_)), "{}", error),
+        }
+    }
+
+    let mut parallel = ParallelIDAStar::new().with_threads(4);
+    assert_eq!(parallel.solve(&unsolvable, &SolveOptions::default()).err(), Some(SolveError::Unsolvable));
+    let field = field::Field::from(0xBAC0F478E19623D5).ok().unwrap();
+    assert!(matches!(parallel.solve(&field, &cancelled), Err(SolveError::Cancelled(_))));
+}
+
+
+
+#[test]
+fn test_bidirectional() {
+    use bidirectional::BidirectionalAStar;
+    use solver::{SolveError, SolveOptions, Solver};
+
+    let mut bidirectional = BidirectionalAStar::new();
+    for s in [0x12345678A0BE9FCD, 0x51247308A6BE9FCD, 0x123456789ABCDEF0, 0x123456789ABC0DEF] {
+        let field = field::Field::from(s).ok().unwrap();
+        let expected = astar::IDAStar::with_heuristic(&field, heuristic::LinearConflict).run().ok().unwrap().moves.len();
+        let solution = bidirectional.solve(&field, &SolveOptions::default()).ok().unwrap();
+        assert_eq!(solution.moves.len(), expected, "{:#018X}", s);
+        assert!(solution.states[0] == field);
+        assert!(solution.states.last().unwrap().is_solved());
+    }
+
+    let field = field::Field::from_string("867254301").ok().unwrap();
+    let solution = BidirectionalAStar::with_heuristic(heuristic::LinearConflict).solve(&field, &SolveOptions::default()).ok().unwrap();
+    assert_eq!(solution.moves.len(), 31);
+    assert!(solution.states.last().unwrap().is_solved());
+
+    assert_eq!(bidirectional.solve(&field::Field::new(), &SolveOptions::default()).ok().unwrap().moves.len(), 0);
+    let unsolvable = field::Field::from(0x123456789AFB0EDC).ok().unwrap();
+    assert_eq!(bidirectional.solve(&unsolvable, &SolveOptions::default()).err(), Some(SolveError::Unsolvable));
+    let limited = SolveOptions { max_nodes: Some(10), ..Default::default() };
+    let field = field::Field::from(0xBAC0F478E19623D5).ok().unwrap();
+    assert!(matches!(bidirectional.solve(&field, &limited), Err(SolveError::NodeLimit(_))));
+}
+
+
+
+#[test]
+fn test_weighted_astar() {
+    use solver::{SolveOptions, Solver};
+
+    for (s, optimal) in [(0x51247308A6BE9FCD, 27), (0x48F917BAC02E6D53, 51)] {
+        let field = field::Field::from(s).ok().unwrap();
+        let mut star = astar::AStar::with_heuristic(&field, heuristic::LinearConflict);
+        let exact = star.solve(&field, &SolveOptions::default()).ok().unwrap();
+        assert_eq!(exact.moves.len(), optimal);
+
+        let mut weighted = astar::AStar::with_heuristic(&field, heuristic::LinearConflict).with_weight(2.0);
+        let solution = weighted.solve(&field, &SolveOptions::default()).ok().unwrap();
+        assert!(solution.states.last().unwrap().is_solved());
+        assert!(solution.moves.len() >= optimal && solution.moves.len() <= 2 * optimal, "{:#018X}", s);
+        assert!(solution.stats.expanded < exact.stats.expanded);
+
+        // Out of nodes, what it reports as the least a solution needs has to hold
+        for weight in [1.0, 3.0] {
+            let limited = SolveOptions { max_nodes: Some(20), ..Default::default() };
+            let mut star = astar::AStar::with_heuristic(&field, heuristic::LinearConflict).with_weight(weight);
+            let lower_bound = star.solve(&field, &limited).err().unwrap().lower_bound().unwrap();
+            assert!(lower_bound > 0.0 && lower_bound <= optimal as f32, "{:#018X} at weight {} claims {}", s, weight, lower_bound);
+        }
+    }
+}
+
+
+
+#[test]
+fn test_anytime_astar() {
+    use solver::{SolveError, SolveOptions, Solver};
+    use std::sync::{Arc, Mutex};
+
+    let field = field::Field::from(0x48F917BAC02E6D53).ok().unwrap();
+    let improvements: Arc<Mutex<Vec<(usize, f32)>>> = Default::default();
+    let improvements_sink = improvements.clone();
+    let mut anytime = astar::AnytimeAStar::with_heuristic(&field, heuristic::LinearConflict, 3.0)
+        .on_improvement(Arc::new(move |solution: &astar::AStarSolution, bound: f32| {
+            improvements_sink.lock().unwrap().push((solution.moves.len(), bound));
+        }));
+
+    // Ends up optimal, every improvement shorter than the last with a bound that holds
+    let solution = anytime.solve(&field, &SolveOptions::default()).ok().unwrap();
+    assert_eq!(solution.moves.len(), 51);
+    assert_eq!(anytime.suboptimality(), 1.0);
+    let improvements = improvements.lock().unwrap().clone();
+    assert!(!improvements.is_empty());
+    assert!(improvements.windows(2).all(|pair| pair[1].0 < pair[0].0));
+    assert!(improvements.iter().all(|&(length, bound)| bound >= 1.0 && length as f32 <= 51.0 * bound));
+
+    // Out of budget after the first solution, still get the best so far
+    let first = improvements[0].0;
+    let limited = SolveOptions { max_nodes: Some(500), ..Default::default() };
+    let solution = anytime.solve(&field, &limited).ok().unwrap();
+    assert!(solution.moves.len() >= 51 && solution.moves.len() <= first);
+    assert!(anytime.suboptimality() >= 1.0 && solution.moves.len() as f32 <= 51.0 * anytime.suboptimality());
+
+    let hard = field::Field::from(0xBAC0F478E19623D5).ok().unwrap();
+    let tiny = SolveOptions { max_nodes: Some(10), ..Default::default() };
+    assert!(matches!(anytime.solve(&hard, &tiny), Err(SolveError::NodeLimit(_))));
+    assert_eq!(anytime.solve(&field::Field::from(0x123456789AFB0EDC).ok().unwrap(), &SolveOptions::default()).err(), Some(SolveError::Unsolvable));
+}
+
+
+
+#[test]
+fn test_custom_goals() {
+    use heuristic::{GoalLinearConflict, GoalManhattan, Heuristic};
+    use solver::{SolveError, SolveOptions, Solver};
+
+    assert_eq!(field::Field::spiral(4, 4).get_string(), "1234CDE5B0F6A987");
+    assert_eq!(field::Field::spiral(3, 3).get_string(), "123804765");
+    assert_eq!(field::Field::spiral(3, 2).get_string(), "123054");
+    assert_eq!(field::Field::blank_first(3, 3).get_string(), "012345678");
+
+    // Toward the usual goal the goal aware heuristics agree with the plain ones
+    let field = field::Field::from(0x51247308A6BE9FCD).ok().unwrap();
+    let solved = field::Field::new();
+    assert_eq!(GoalManhattan::new(&solved).evaluate(&field), heuristic::Manhattan.evaluate(&field));
+    assert_eq!(GoalLinearConflict::new(&solved).evaluate(&field), heuristic::LinearConflict.evaluate(&field));
+
+    let goal = field::Field::spiral(4, 4);
+    let swapped = field::Field::from_string("2134CDE5B0F6A987").ok().unwrap();
+    assert!(goal.is_solvable_toward(&goal));
+    assert!(!swapped.is_solvable_toward(&goal));
+    assert!(!field::Field::spiral(3, 3).is_solvable_toward(&goal));
+
+    // Scramble the goal and check every solver finds its way back
+    let mut start = goal;
+    let mut previous: Option<field::Move> = None;
+    for i in 0..40_usize {
+        let moves: Vec<field::Move> = field::Move::iter().collect();
+        let m = (0..4).map(|k| moves[(i * 7 + i / 3 + k) % 4]).find(|&m| start.is_valid_move(m) && previous != Some(m.inverse())).unwrap();
+        start.make_move(m);
+        previous = Some(m);
+    }
+
+    let heuristic = GoalLinearConflict::new(&goal);
+    for m in field::Move::iter().filter(|&m| start.is_valid_move(m)) {
+        let mut moved = start;
+        moved.make_move(m);
+        assert_eq!(heuristic.next(&start, heuristic.evaluate(&start), m), heuristic.evaluate(&moved));
+    }
+
+    let optimal = astar::IDAStar::with_heuristic(&start, heuristic).run().ok().unwrap();
+    assert!(optimal.moves.len() >= 16 && optimal.moves.len() <= 40);
+    let mut solvers: Vec<Box<dyn Solver>> = vec![
+        Box::new(astar::AStar::with_heuristic(&start, heuristic)),
+        Box::new(astar::AStar::with_heuristic(&start, GoalManhattan::new(&goal))),
+        Box::new(parallel::ParallelIDAStar::with_heuristic(heuristic).with_threads(2)),
+        Box::new(bidirectional::BidirectionalAStar::with_heuristic(heuristic)),
+        Box::new(astar::AnytimeAStar::with_heuristic(&start, heuristic, 2.0)),
+    ];
+    for solver in solvers.iter_mut() {
+        let solution = solver.solve(&start, &SolveOptions::default()).ok().unwrap();
+        assert_eq!(solution.moves.len(), optimal.moves.len());
+        assert!(*solution.states.last().unwrap() == goal);
+        assert_eq!(solver.solve(&swapped, &SolveOptions::default()).err(), Some(SolveError::Unsolvable));
+    }
+
+    // Pattern databases can be built toward any goal too
+    let goal = field::Field::blank_first(3, 3);
+    let start = field::Field::from_string("876543210").ok().unwrap();
+    let pdb = pdb::PatternDatabase::build_for_goal(&goal, &[&[1, 2, 3, 4], &[5, 6, 7, 8]]);
+    assert!(*pdb.goal() == goal);
+    assert_eq!(pdb.evaluate(&goal), 0.0);
+    let expected = astar::IDAStar::with_heuristic(&start, GoalLinearConflict::new(&goal)).run().ok().unwrap().moves.len();
+    let solution = astar::IDAStar::with_heuristic(&start, pdb).run().ok().unwrap();
+    assert_eq!(solution.moves.len(), expected);
+    assert!(*solution.states.last().unwrap() == goal);
+}
+
+
+
+#[test]
+fn test_scramble() {
+    use solver::Solver;
+
+    let goal = field::Field::with_size(4, 4);
+    assert!(field::Field::from_cells(4, 4, &goal.cells()).unwrap() == goal);
+    assert!(field::Field::from_cells(2, 2, &[1, 1, 2, 0]).is_err());
+
+    let mut rng = scramble::Rng::new(1);
+    for _ in 0..100 {
+        assert!(scramble::uniform(&goal, &mut rng).is_solvable());
+    }
+    assert!(scramble::uniform(&goal, &mut scramble::Rng::new(5)) == scramble::uniform(&goal, &mut scramble::Rng::new(5)));
+
+    // A 2x2 has 12 solvable boards, they should all come up about as often
+    let small = field::Field::with_size(2, 2);
+    let mut counts = std::collections::HashMap::new();
+    for _ in 0..12000 {
+        *counts.entry(scramble::uniform(&small, &mut rng).unique_id()).or_insert(0) += 1;
+    }
+    assert_eq!(counts.len(), 12);
+    assert!(counts.values().all(|&count| (800..1200).contains(&count)));
+
+    let goal = field::Field::spiral(3, 3);
+    let mut solver = astar::IDAStar::with_heuristic(&goal, heuristic::GoalLinearConflict::new(&goal));
+    for length in [0, 1, 12, 20] {
+        let board = scramble::with_optimal_length(&goal, length, &mut rng, |board| solver.solve(board, &Default::default())).unwrap().unwrap();
+        assert_eq!(solver.solve(&board, &Default::default()).ok().unwrap().moves.len(), length);
+    }
+
+    let mut solver = astar::IDAStar::with_heuristic(&small, heuristic::Manhattan);
+    assert!(scramble::with_optimal_length(&small, 7, &mut rng, |board| solver.solve(board, &Default::default())).unwrap().is_none());
+}
+
+
+
+#[test]
+fn test_rank() {
+    use rank::{lex_rank, lex_unrank, mr_rank, mr_unrank, placements};
+
+    assert_eq!(placements(16, 6), 5765760);
+    assert_eq!(placements(25, 25), (1..=25_u128).product());
+
+    // Both orders hit every rank exactly once, and unranking undoes ranking
+    for (n, k) in [(5, 5), (6, 3), (9, 4), (4, 0)] {
+        let count = placements(n as usize, k) as usize;
+        for (unrank, rank) in [(lex_unrank as fn(u128, u8, usize) -> Vec<u8>, lex_rank as fn(&[u8], u8) -> u128), (mr_unrank, mr_rank)] {
+            let mut seen = vec![false; count];
+            for r in 0..count as u128 {
+                let positions = unrank(r, n, k);
+                assert_eq!(positions.len(), k);
+                assert!(positions.iter().all(|&p| p < n));
+                assert_eq!(rank(&positions, n), r);
+                assert!(!std::mem::replace(&mut seen[r as usize], true));
+            }
+        }
+    }
+
+    // Lexicographic ranks keep placements sorted
+    for r in 1..placements(6, 4) {
+        assert!(lex_unrank(r - 1, 6, 4) < lex_unrank(r, 6, 4));
+    }
+
+    let mut rng = scramble::Rng::new(16);
+    for (width, height) in [(4, 4), (5, 5), (3, 2)] {
+        let goal = field::Field::with_size(width, height);
+        for _ in 0..50 {
+            let board = scramble::uniform(&goal, &mut rng);
+            assert!(field::Field::from_lex_rank(width, height, board.lex_rank()).unwrap() == board);
+            assert!(field::Field::from_mr_rank(width, height, board.mr_rank()).unwrap() == board);
+
+            let tiles = [3, 1, 5];
+            assert_eq!(lex_unrank(board.partial_lex_rank(&tiles), board.cell_count(), 3), board.tile_positions(&tiles));
+            assert_eq!(mr_unrank(board.partial_mr_rank(&tiles), board.cell_count(), 3), board.tile_positions(&tiles));
+        }
+    }
+    assert_eq!(field::Field::with_size(3, 3).lex_rank(), lex_rank(&[8, 0, 1, 2, 3, 4, 5, 6, 7], 9));
+    assert!(field::Field::from_lex_rank(3, 3, placements(9, 9)).is_err());
+}
+
+
+
+#[test]
+fn test_enumerate() {
+    use heuristic::Heuristic;
+    use solver::Solver;
+
+    let space = enumerate::StateSpace::explore(&field::Field::with_size(3, 2));
+    assert_eq!((space.states(), space.max_depth()), (360, 21));
+
+    let space = enumerate::StateSpace::explore(&field::Field::with_size(4, 2));
+    assert_eq!((space.states(), space.max_depth()), (20160, 36));
+    assert_eq!(space.hardest().iter().map(field::Field::get_string).collect::<Vec<String>>(), ["07214365"]);
+
+    // Every heuristic has to stay at or under the real distance
+    let goal = field::Field::with_size(3, 3);
+    let partition = pdb::default_partition(3, 3);
+    let pdb = pdb::PatternDatabase::build(3, 3, &partition.iter().map(Vec::as_slice).collect::<Vec<&[u8]>>());
+    let space = enumerate::StateSpace::explore_with(&goal, |board, depth| {
+        let depth = depth as f32;
+        assert!(heuristic::Manhattan.evaluate(board) <= depth);
+        assert!(heuristic::LinearConflict.evaluate(board) <= depth);
+        assert!(pdb.evaluate(board) <= depth);
+    });
+    assert_eq!(space.states(), 181440);
+    assert_eq!(space.depth_counts()[..4], [1, 2, 4, 8]);
+    assert_eq!(space.hardest().len(), 2);
+    for board in space.hardest() {
+        let solution = astar::IDAStar::with_heuristic(board, pdb::PatternDatabase::build(3, 3, &[&[1, 2, 3, 4], &[5, 6, 7, 8]]))
+            .solve(board, &Default::default()).ok().unwrap();
+        assert_eq!(solution.moves.len(), 31);
+    }
+}
+
+
+
+#[test]
+fn test_verify() {
+    use field::Move;
+    use solver::{replay, verify, Solver, VerifyError};
+
+    let goal = field::Field::with_size(4, 4);
+    let start = field::Field::from(0x123456789ABC0DEF).ok().unwrap();
+    let moves = [Move::Right, Move::Right, Move::Right];
+    assert!(verify(&start, &moves, &goal).is_ok());
+    assert_eq!(replay(&start, &moves).ok().unwrap().len(), 4);
+
+    let error = verify(&start, &[Move::Right, Move::Down, Move::Right], &goal).err().unwrap();
+    assert_eq!(error.bad_move(), Some(1));
+    assert!(matches!(error, VerifyError::IllegalMove { index: 1, m: Move::Down, .. }));
+    assert!(error.board().unwrap() == field::Field::from(0x123456789ABCD0EF).ok().unwrap());
+
+    let error = verify(&start, &moves[..2], &goal).err().unwrap();
+    assert!(matches!(error, VerifyError::NotSolved { .. }) && error.bad_move().is_none());
+    assert!(verify(&goal, &[], &goal).is_ok());
+
+    let mut solution = astar::AStar::new(&start).solve(&start, &Default::default()).ok().unwrap();
+    assert!(solution.verify(&goal).is_ok());
+    solution.states.swap(1, 2);
+    assert!(matches!(solution.verify(&goal), Err(VerifyError::WrongState { index: 1 })));
+    solution.states.truncate(1);
+    assert!(matches!(solution.verify(&goal), Err(VerifyError::WrongState { index: 1 })));
+}
+
+
+
+#[test]
+fn test_move_sequence() {
+    use field::Move;
+    use moves::{MoveSequence, Notation};
+
+    let start = field::Field::from(0x51247308A6BE9FCD).ok().unwrap();
+    let goal = field::Field::new();
+
+    let sequence: MoveSequence = "^v<> udlr, UDLR".parse().unwrap();
+    assert_eq!(sequence.len(), 12);
+    assert_eq!(sequence.to_string(), "^v<>^v<>^v<>");
+    assert!("^x".parse::<MoveSequence>().is_err());
+    assert_eq!("^3 <2 R".parse::<MoveSequence>().unwrap().moves(), [Move::Up, Move::Up, Move::Up, Move::Left, Move::Left, Move::Right]);
+
+    let solution: MoveSequence = "vv>^<^<<^>>v<vv>>^<<<v>>^>v".parse().unwrap();
+    assert!(solution.apply(&start).ok().unwrap() == goal);
+    for notation in [Notation::Arrows, Notation::Letters, Notation::Compressed, Notation::Tiles] {
+        let written = solution.format(notation, &start).ok().unwrap();
+        assert_eq!(MoveSequence::parse(&written, &start).unwrap(), solution, "{}", written);
+    }
+    assert_eq!(solution.format(Notation::Letters, &start).ok().unwrap(), "DDRULULLURRDLDDRRULLLDRRURD");
+    assert_eq!(solution.format(Notation::Compressed, &start).ok().unwrap(), "v2>^<^<2^>2v<v2>2^<3v>2^>v");
+    assert_eq!(solution.format(Notation::Tiles, &start).ok().unwrap().split(' ').next(), Some("11"));
+    assert!(MoveSequence::parse("1", &start).is_err());
+    assert!("^^^^".parse::<MoveSequence>().unwrap().format(Notation::Tiles, &start).is_err());
+
+    assert!(solution.invert().apply(&goal).ok().unwrap() == start);
+    assert_eq!(solution.invert().invert(), solution);
+    assert!("^<>v".parse::<MoveSequence>().unwrap().cancel_inverses().is_empty());
+    let padded: MoveSequence = format!("^v{}<>", solution).parse().unwrap();
+    assert_eq!(padded.cancel_inverses(), solution);
+    assert!(padded.apply(&start).ok().unwrap() == goal);
+}
+
+
+
+#[test]
+fn test_all_optimal_solutions() {
+    use std::collections::{HashMap, HashSet};
+    use astar::AllSolutionsOptions;
+    use field::Move;
+
+    let goal = field::Field::with_size(3, 3);
+    let mut rng = scramble::Rng::new(20);
+    for _ in 0..5 {
+        let start = scramble::random_walk(&goal, 40, &mut rng);
+
+        // Count shortest paths breadth first
+        let mut paths: HashMap<u128, (usize, u64)> = HashMap::from([(start.unique_id(), (0, 1))]);
+        let mut frontier = vec![start];
+        while !paths.contains_key(&goal.unique_id()) {
+            let mut next = Vec::new();
+            for board in &frontier {
+                let (depth, count) = paths[&board.unique_id()];
+                for m in Move::iter() {
+                    let mut neighbour = *board;
+                    if !neighbour.make_move(m) { continue }
+                    let entry = paths.entry(neighbour.unique_id()).or_insert((depth + 1, 0));
+                    if entry.0 != depth + 1 { continue }
+                    if entry.1 == 0 { next.push(neighbour) }
+                    entry.1 += count;
+                }
+            }
+            frontier = next;
+        }
+        let (length, count) = paths[&goal.unique_id()];
+
+        let found = astar::IDAStar::with_heuristic(&start, heuristic::LinearConflict)
+            .solve_all(&start, &Default::default(), &Default::default()).ok().unwrap();
+        assert_eq!((found.length, found.count), (length, count));
+        assert_eq!(found.solutions.len() as u64, count);
+        assert_eq!(found.solutions.iter().map(|moves| moves::MoveSequence::from(moves.as_slice()).to_string()).collect::<HashSet<String>>().len() as u64, count);
+        assert!(found.solutions.iter().all(|moves| moves.len() == length && solver::verify(&start, moves, &goal).is_ok()));
+
+        let capped = AllSolutionsOptions { max_solutions: Some(2), ..Default::default() };
+        let found = astar::IDAStar::new(&start).solve_all(&start, &Default::default(), &capped).ok().unwrap();
+        assert_eq!(found.count, count);
+        assert_eq!(found.solutions.len() as u64, count.min(2));
+
+        // Without transpositions no two solutions share a board on the way
+        let distinct = AllSolutionsOptions { remove_transpositions: true, ..Default::default() };
+        let found = astar::IDAStar::new(&start).solve_all(&start, &Default::default(), &distinct).ok().unwrap();
+        assert!(found.count >= 1 && found.count <= count);
+        let mut seen = HashSet::new();
+        for moves in &found.solutions {
+            let states = solver::replay(&start, moves).ok().unwrap();
+            assert!(states[1..length].iter().all(|board| seen.insert(board.unique_id())));
+        }
+    }
+
+    let found = astar::IDAStar::new(&goal).solve_all(&goal, &Default::default(), &Default::default()).ok().unwrap();
+    assert_eq!((found.length, found.count), (0, 1));
+}
+
+
+
+#[test]
+fn test_line_shift() {
+    use field::Move;
+
+    // Blank in the middle of the second row
+    let board = field::Field::from_string_sized(4, 3, "12345608A79B").unwrap();
+    assert_eq!(board.get_empty_index(), 6);
+    assert_eq!(board.line_moves(4), Some(vec![Move::Left, Move::Left]));
+    assert_eq!(board.line_moves(7), Some(vec![Move::Right]));
+    assert_eq!(board.line_moves(2), Some(vec![Move::Up]));
+    assert_eq!(board.line_moves(10), Some(vec![Move::Down]));
+    assert_eq!(board.line_moves(6), None);
+    assert_eq!(board.line_moves(0), None);
+    assert_eq!(board.line_moves(12), None);
+
+    let mut shifted = board;
+    assert_eq!(shifted.shift_line(4).map(|moves| moves.len()), Some(2));
+    assert_eq!(shifted.get_string(), "12340568A79B");
+    assert_eq!(shifted.shift_line(8), Some(vec![Move::Down]));
+    assert_eq!(shifted.get_string(), "1234A568079B");
+    assert!(shifted.shift_line(5).is_none());
+    assert_eq!(shifted.get_string(), "1234A568079B");
+}
+
+
+
+#[cfg(feature = "gui")]
+#[test]
+fn test_cell_at() {
+    use gui::{cell_at, CELL_SIZE};
+
+    let board = field::Field::with_size(4, 3);
+    let size = CELL_SIZE as f32;
+    assert_eq!(cell_at(&board, 0.0, 0.0), Some(0));
+    assert_eq!(cell_at(&board, size * 3.5, size * 0.5), Some(3));
+    assert_eq!(cell_at(&board, size * 0.5, size * 2.9), Some(8));
+    assert_eq!(cell_at(&board, size * 4.0, 1.0), None);
+    assert_eq!(cell_at(&board, 1.0, size * 3.0), None);
+    assert_eq!(cell_at(&board, -1.0, 1.0), None);
+}
+
+
+
+#[cfg(feature = "gui")]
+#[test]
+fn test_playback() {
+    use gui::Playback;
+
+    let start = field::Field::from_string_sized(3, 2, "123045").unwrap();
+    let solution = astar::AStarSolution::from_moves(&start, vec![Move::Right, Move::Right], solver::SearchStats::default());
+    let mut playback = Playback::new(&solution);
+    assert_eq!((playback.current(), playback.len()), (0, 2));
+    assert!(*playback.frame().0 == start && playback.frame().1.is_none());
+
+    // Stepping slides the tile over a few frames
+    playback.step_forward();
+    playback.update(0.25);
+    let (board, sliding) = playback.frame();
+    assert!(*board == start);
+    assert_eq!(sliding, Some((4, 0.5)));
+    assert_eq!(playback.current(), 0);
+    playback.update(1.0);
+    assert_eq!(playback.current(), 1);
+    assert!(*playback.frame().0 == solution.states[1]);
+
+    playback.step_back();
+    playback.update(0.25);
+    assert_eq!(playback.current(), 1);
+    assert_eq!(playback.frame().1, Some((4, 0.5)));
+    playback.update(1.0);
+    assert_eq!(playback.current(), 0);
+
+    // Playing runs to the end and stops there, and starts over from the end
+    playback.faster();
+    assert_eq!(playback.speed(), 4.0);
+    playback.toggle();
+    playback.update(0.25);
+    assert!(playback.is_playing());
+    playback.update(1.0);
+    assert!(!playback.is_playing());
+    assert_eq!(playback.current(), 2);
+    assert!(*playback.frame().0 == solution.states[2] && playback.frame().1.is_none());
+    playback.play();
+    assert_eq!(playback.current(), 0);
+
+    // Pausing lets the sliding tile finish
+    playback.update(0.375);
+    playback.pause();
+    playback.update(1.0);
+    assert_eq!(playback.current(), 2);
+
+    // Stepping while playing goes from the tile that's sliding
+    playback.play();
+    playback.update(0.125);
+    playback.step_forward();
+    assert!(!playback.is_playing());
+    playback.update(1.0);
+    assert_eq!(playback.current(), 1);
+    playback.play();
+    playback.update(0.125);
+    playback.step_back();
+    playback.update(1.0);
+    assert_eq!(playback.current(), 1);
+    for _ in 0..10 { playback.slower() }
+    assert_eq!(playback.speed(), Playback::MIN_SPEED);
+}
+
+
+
+#[cfg(feature = "gui")]
+#[test]
+fn test_hint() {
+    use std::time::Duration;
+    use gui::{Hint, HINT_TIME};
+    use solver::CancelToken;
+
+    let board = field::Field::from_string("51247308A6BE9FCD").unwrap();
+    let hint = Hint::find(&board, &field::Field::with_size(4, 4), HINT_TIME, &CancelToken::new()).unwrap();
+    assert!(hint.optimal);
+    assert_eq!(hint.distance(), 27);
+    assert!(solver::verify(&board, &hint.moves, &field::Field::with_size(4, 4)).is_ok());
+    assert_eq!(hint.next_tile(), board.moved_piece_index(hint.moves[0]));
+
+    // Following the hint keeps it, straying from it drops it
+    let mut next = board;
+    next.make_move(hint.moves[0]);
+    let followed = hint.follow(&next).unwrap();
+    assert_eq!(followed.distance(), 26);
+    assert_eq!(followed.moves, hint.moves[1..]);
+    assert!(hint.follow(&field::Field::with_size(4, 4)).is_some_and(|hint| hint.next_tile().is_none()));
+    let mut strayed = board;
+    strayed.make_move(hint.moves[0].inverse());
+    assert!(hint.follow(&strayed).is_none());
+
+    // No time for IDA* gets a weighted A* solution, and cancelling gets nothing
+    let hard = field::Field::from_string("48F917BAC02E6D53").unwrap();
+    let fallback = Hint::find(&hard, &field::Field::with_size(4, 4), Duration::ZERO, &CancelToken::new()).unwrap();
+    assert!(!fallback.optimal);
+    assert!(solver::verify(&hard, &fallback.moves, &field::Field::with_size(4, 4)).is_ok());
+    let cancel = CancelToken::new();
+    cancel.cancel();
+    assert!(Hint::find(&hard, &field::Field::with_size(4, 4), HINT_TIME, &cancel).is_none());
+
+    // Hints head for whatever goal the game has
+    let goal = field::Field::blank_first(3, 3);
+    let start = field::Field::from_string("123456780").unwrap();
+    let hint = Hint::find(&start, &goal, HINT_TIME, &CancelToken::new()).unwrap();
+    assert!(hint.optimal);
+    assert!(solver::verify(&start, &hint.moves, &goal).is_ok());
+}
+
+
+
+#[test]
+fn test_session() {
+    use std::time::Duration;
+    use session::{format_time, Session};
+
+    let start = field::Field::from_string_sized(3, 2, "123045").unwrap();
+    let mut session = Session::new(&start, &field::Field::with_size(3, 2));
+    let mut board = start;
+
+    // The clock waits for the first move
+    session.tick(Duration::from_secs(5));
+    assert_eq!(session.elapsed(), Duration::ZERO);
+    assert!(!session.is_running());
+
+    board.make_move(Move::Up);
+    assert!(session.record(1, &board).is_none());
+    session.tick(Duration::from_millis(1500));
+    session.hint_used();
+    board.make_move(Move::Down);
+    assert!(session.record(1, &board).is_none());
+    session.set_optimal(2);
+    let shifted = board.shift_line(5).unwrap();
+    let victory = session.record(shifted.len(), &board).unwrap();
+    assert_eq!(victory.moves, 4);
+    assert_eq!(victory.hints, 1);
+    assert_eq!(victory.elapsed, Duration::from_millis(1500));
+    assert_eq!(victory.optimal, Some(2));
+    assert_eq!(victory.efficiency(), Some(0.5));
+    assert_eq!(victory.to_string(), "Solved in 4 moves and 0:01.5, 2 is optimal, 50% efficient, 1 hints");
+
+    // Nothing counts after the win
+    assert!(session.is_finished() && !session.is_running());
+    session.tick(Duration::from_secs(5));
+    board.make_move(Move::Left);
+    assert!(session.record(1, &board).is_none());
+    assert_eq!((session.moves(), session.elapsed()), (4, Duration::from_millis(1500)));
+    assert!(session.victory() == Some(&victory));
+
+    // An optimal length found late still makes it onto the victory
+    let mut late = Session::new(&start, &field::Field::with_size(3, 2));
+    let victory = late.record(2, &field::Field::with_size(3, 2)).unwrap();
+    assert_eq!(victory.efficiency(), None);
+    assert_eq!(victory.to_string(), "Solved in 2 moves and 0:00.0");
+    late.set_optimal(2);
+    assert_eq!(late.victory().unwrap().efficiency(), Some(1.0));
+
+    // Wins are judged against the session's goal, not the usual solved board
+    let goal = field::Field::from_string_sized(3, 2, "123405").unwrap();
+    let mut board = field::Field::from_string_sized(3, 2, "123045").unwrap();
+    let mut custom = Session::new(&board, &goal);
+    assert!(!custom.is_finished());
+    board.shift_line(5);
+    assert!(board.is_solved());
+    assert!(custom.record(2, &board).is_none());
+    board.shift_line(4);
+    assert_eq!(custom.record(1, &board).unwrap().moves, 3);
+
+    // Starting on the goal is a win in no moves
+    let solved = Session::new(&goal, &goal);
+    assert_eq!(solved.victory().map(|victory| (victory.moves, victory.efficiency())), Some((0, Some(1.0))));
+
+    assert_eq!(format_time(Duration::from_millis(62_500)), "1:02.5");
+    assert_eq!(format_time(Duration::from_millis(59_940)), "0:59.9");
+    assert_eq!(format_time(Duration::from_millis(59_960)), "1:00.0");
+    assert_eq!(format_time(Duration::from_millis(119_970)), "2:00.0");
+}